@@ -0,0 +1,34 @@
+use game_ai::random::Xorshift;
+use game_ai::use_case::single_player_game_with_no_context::GameState;
+use rand::{Rng, SeedableRng};
+
+fn main() {
+    // 近傍を number 回評価するのにかかった秒数を、transition して get_score で計算し直す場合と、
+    // transition_with_delta で差分計算する場合で比べる。cargo run --release --example benchmark_delta_evaluation [number]
+    let number = std::env::args().nth(1).map_or(1_000_000, |number| number.parse::<usize>().expect("number must be an integer"));
+    let mut rng = Xorshift::seed_from_u64(0);
+    let mut state = GameState::new(rng.gen::<u64>());
+    state.init(&mut rng);
+    state.transition_with_delta(&mut rng);
+    let start_time = std::time::Instant::now();
+    let mut sum = 0;
+    for _ in 0..number {
+        let mut now_state = state;
+        now_state.transition(&mut rng);
+        sum += now_state.get_score();
+    }
+    std::hint::black_box(sum);
+    let full_time = start_time.elapsed().as_secs_f64();
+    let start_time = std::time::Instant::now();
+    let mut sum = 0;
+    for _ in 0..number {
+        let mut now_state = state;
+        sum += match now_state.transition_with_delta(&mut rng) {
+            Some(score) => score,
+            None => now_state.get_score(),
+        };
+    }
+    std::hint::black_box(sum);
+    let delta_time = start_time.elapsed().as_secs_f64();
+    println!("full: {:.3}s, delta: {:.3}s, speedup: {:.2}x", full_time, delta_time, full_time / delta_time);
+}
@@ -0,0 +1,25 @@
+use game_ai::random::Xorshift;
+use rand::{Rng, SeedableRng};
+
+fn main() {
+    // 焼きなましの内側と同じく、範囲つきの整数と 0 以上 1 未満の実数を number 回ずつ作るのにかかった秒数を、
+    // StdRng と Xorshift それぞれについて測る。cargo run --release --example benchmark_rng [number]
+    let number = std::env::args().nth(1).map_or(100_000_000, |number| number.parse::<usize>().expect("number must be an integer"));
+    let mut std_rng = rand::rngs::StdRng::seed_from_u64(0);
+    let start_time = std::time::Instant::now();
+    let mut sum = 0.0;
+    for _ in 0..number {
+        sum += (std_rng.gen::<usize>() % 1200) as f64 + std_rng.gen::<f64>();
+    }
+    std::hint::black_box(sum);
+    let std_rng_time = start_time.elapsed().as_secs_f64();
+    let mut xorshift = Xorshift::seed_from_u64(0);
+    let start_time = std::time::Instant::now();
+    let mut sum = 0.0;
+    for _ in 0..number {
+        sum += xorshift.next_index(1200) as f64 + xorshift.next_f64();
+    }
+    std::hint::black_box(sum);
+    let xorshift_time = start_time.elapsed().as_secs_f64();
+    println!("StdRng: {:.3}s, Xorshift: {:.3}s", std_rng_time, xorshift_time);
+}
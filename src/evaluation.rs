@@ -0,0 +1,112 @@
+use rand::Rng;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::statistics::ScoreSummary;
+use crate::time_keeper::CancellationToken;
+
+// play_game に渡す探索の持ち時間の、time_threshold に対する割合。
+// 探索は持ち時間を少し過ぎてから止まり、初期化や後片付けにも時間がかかるので、time_threshold の残りはその余裕にする。
+#[allow(unused)]
+pub const SEARCH_TIME_RATIO: f64 = 0.9;
+
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct EvaluationResult {
+    // seeds の順に並んだスコアの要約と、各ゲームにかかった秒数。
+    pub summary: ScoreSummary,
+    pub elapsed_times: Vec<f64>,
+    // time_threshold を超えたゲームの数。
+    pub time_over_number: usize,
+}
+impl std::fmt::Display for EvaluationResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let max_elapsed_time = self.elapsed_times.iter().copied().fold(0.0, f64::max);
+        write!(f, "{}, max time: {:.3}s, time over: {}", self.summary, max_elapsed_time, self.time_over_number)
+    }
+}
+
+#[allow(unused)]
+pub fn game_seeds(game_number: usize, seed: u64) -> Vec<u64> {
+    // seed からゲームごとのシードの列を作る。AI の評価はどれもこれを使うので、同じ seed なら同じゲームの列で比べられる。
+    // 同じ seed なら game_number を増やしても先頭のシードは変わらない。
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    (0..game_number).map(|_| rng.gen::<u64>()).collect()
+}
+
+#[allow(unused)]
+pub fn evaluate(seeds: &[u64], thread_number: usize, time_threshold: f64, play_game: &(dyn Fn(u64, f64) -> isize + Sync)) -> EvaluationResult {
+    // seeds の各シードで play_game(seed, time_threshold * SEARCH_TIME_RATIO) を thread_number 本のスレッドで分担して実行し、
+    // 一ゲームにかかった時間が time_threshold を超えたものを time over として数える。
+    // 結果はシードの位置に書き込むので、play_game が決定的ならスレッド数によらず同じ結果になる。
+    // 時間制限のある探索を公平に比べるため、thread_number は物理コア数以下にする。0 なら利用できる並列数を使う。
+    // CancellationToken::run の中から呼ばれたときは、その旗を各スレッドにも渡し、cancel されたら残りのゲームも時間切れとして進める。
+    let thread_number = if thread_number == 0 {
+        std::thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        thread_number
+    };
+    let cancellation_token = CancellationToken::current();
+    let next_index = AtomicUsize::new(0);
+    let results = Mutex::new(vec![(0, 0.0); seeds.len()]);
+    std::thread::scope(|scope| {
+        for _ in 0..thread_number.min(seeds.len()) {
+            scope.spawn(|| loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                if index >= seeds.len() {
+                    break;
+                }
+                let start_time = std::time::Instant::now();
+                let search_time_threshold = time_threshold * SEARCH_TIME_RATIO;
+                let score = match &cancellation_token {
+                    Some(cancellation_token) => cancellation_token.run(|| play_game(seeds[index], search_time_threshold)),
+                    None => play_game(seeds[index], search_time_threshold),
+                };
+                let elapsed_time = start_time.elapsed().as_secs_f64();
+                results.lock().unwrap()[index] = (score, elapsed_time);
+            });
+        }
+    });
+    let results = results.into_inner().unwrap();
+    EvaluationResult {
+        summary: ScoreSummary::build(seeds.iter().zip(results.iter()).map(|(&seed, &(score, _))| (seed, score)).collect()),
+        elapsed_times: results.iter().map(|&(_, elapsed_time)| elapsed_time).collect(),
+        time_over_number: results.iter().filter(|&&(_, elapsed_time)| elapsed_time > time_threshold).count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time_keeper::TimeKeeper;
+
+    #[test]
+    fn cancellation_reaches_worker_threads() {
+        // cancel されたら worker のスレッドの TimeKeeper が時間切れになり、ゲームは time_threshold を待たずに終わる。
+        // cancel が届かなくても各ゲームは 2 秒で終わるので、テストは止まらずに失敗する。
+        let token = CancellationToken::new();
+        let play_game = |seed: u64, _: f64| {
+            let time_keeper = TimeKeeper::build(2.0);
+            while !time_keeper.is_time_over() {
+                std::thread::yield_now();
+            }
+            seed as isize
+        };
+        let start_time = std::time::Instant::now();
+        let result = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                token.cancel();
+            });
+            token.run(|| evaluate(&[1, 2, 3, 4], 2, f64::INFINITY, &play_game))
+        });
+        assert_eq!(result.elapsed_times.len(), 4);
+        assert!(start_time.elapsed().as_secs_f64() < 1.0, "{:?}", result.elapsed_times);
+    }
+
+    #[test]
+    fn game_seeds_keep_their_prefix() {
+        assert_eq!(game_seeds(5, 7), game_seeds(10, 7)[..5]);
+        assert_ne!(game_seeds(5, 7), game_seeds(5, 8));
+    }
+}
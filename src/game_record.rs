@@ -0,0 +1,131 @@
+#[allow(unused)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameRecord {
+    // 初期状態を作ったシード値。
+    pub seed: u64,
+    // 初期盤面を一行ずつ文字列にしたもの。書き方はゲームごとに決める。
+    pub initial_board: Vec<String>,
+    // 各ターンの action を文字列にしたもの。
+    pub actions: Vec<String>,
+    // 各ターンの action の後のスコア。actions と同じ長さになる。
+    pub scores: Vec<isize>,
+}
+#[allow(unused)]
+impl GameRecord {
+    pub fn new(seed: u64, initial_board: Vec<String>) -> Self {
+        Self {
+            seed,
+            initial_board,
+            actions: Vec::new(),
+            scores: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, action: String, score: isize) {
+        self.actions.push(action);
+        self.scores.push(score);
+    }
+
+    pub fn final_score(&self) -> Option<isize> {
+        self.scores.last().copied()
+    }
+
+    pub fn first_difference(&self, other: &Self) -> Option<usize> {
+        // 二つの記録で action かスコアが最初に食い違うターンを返す。片方が短ければ短い方の長さを返す。
+        let turn_number = self.actions.len().min(other.actions.len());
+        (0..turn_number)
+            .find(|&turn| self.actions[turn] != other.actions[turn] || self.scores[turn] != other.scores[turn])
+            .or_else(|| (self.actions.len() != other.actions.len()).then_some(turn_number))
+    }
+
+    pub fn to_text(&self) -> String {
+        // diff をとりやすいように、一行に一つの情報を書く。
+        // seed <seed>
+        // board <行数>
+        // <盤面の各行>
+        // turns <ターン数>
+        // <action> <score>
+        let mut text = format!("seed {}\nboard {}\n", self.seed, self.initial_board.len());
+        for line in self.initial_board.iter() {
+            text += line;
+            text += "\n";
+        }
+        text += &format!("turns {}\n", self.actions.len());
+        for (action, score) in self.actions.iter().zip(self.scores.iter()) {
+            text += &format!("{} {}\n", action, score);
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+        let seed = header_value(lines.next(), "seed")?.parse::<u64>().map_err(|error| format!("invalid seed: {}", error))?;
+        let board_line_number = header_value(lines.next(), "board")?.parse::<usize>().map_err(|error| format!("invalid board line number: {}", error))?;
+        let mut initial_board = Vec::with_capacity(board_line_number);
+        for _ in 0..board_line_number {
+            initial_board.push(lines.next().ok_or("board is shorter than its line number")?.to_string());
+        }
+        let turn_number = header_value(lines.next(), "turns")?.parse::<usize>().map_err(|error| format!("invalid turn number: {}", error))?;
+        let mut record = Self::new(seed, initial_board);
+        for turn in 0..turn_number {
+            let line = lines.next().ok_or(format!("missing turn {}", turn))?;
+            let (action, score) = line.rsplit_once(' ').ok_or(format!("turn {}: expected `<action> <score>` but found `{}`", turn, line))?;
+            let score = score.parse::<isize>().map_err(|error| format!("turn {}: invalid score: {}", turn, error))?;
+            record.push(action.to_string(), score);
+        }
+        // 切り詰められたファイルや、複数の記録をつなげたファイルを一つの記録として読まないよう、残りの行は空行しか許さない。
+        if let Some(line) = lines.find(|line| !line.trim().is_empty()) {
+            return Err(format!("unexpected content after the last turn: `{}`", line));
+        }
+        Ok(record)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_text()).map_err(|error| format!("{}: {}", path, error))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        Self::from_text(&text)
+    }
+}
+impl std::fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_text())
+    }
+}
+
+#[allow(unused)]
+fn header_value<'a>(line: Option<&'a str>, name: &str) -> Result<&'a str, String> {
+    // `<name> <value>` の形の行から value を取り出す。
+    let line = line.ok_or(format!("missing `{}` line", name))?;
+    line.strip_prefix(name)
+        .and_then(|rest| rest.strip_prefix(' '))
+        .ok_or(format!("expected `{} ...` but found `{}`", name, line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_record() -> GameRecord {
+        let mut record = GameRecord::new(u64::MAX, vec!["01@2".to_string(), "a \"quoted\" \\ line".to_string(), "日本語".to_string()]);
+        record.push("R".to_string(), 3);
+        record.push("D".to_string(), -5);
+        record
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let record = sample_record();
+        assert_eq!(GameRecord::from_text(&record.to_text()), Ok(record));
+    }
+
+    #[test]
+    fn text_rejects_trailing_content() {
+        let text = sample_record().to_text();
+        assert!(GameRecord::from_text(&format!("{}\n\n", text)).is_ok());
+        assert!(GameRecord::from_text(&format!("{}{}", text, text)).is_err());
+        assert!(GameRecord::from_text(&format!("{}R 3\n", text)).is_err());
+    }
+}
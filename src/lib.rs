@@ -1,4 +1,16 @@
+pub mod evaluation;
+pub mod game_record;
+pub mod random;
+pub mod search_observer;
+pub mod search_statistics;
 mod single_player_game_with_context;
 mod single_player_game_with_no_context;
-mod time_keeper;
-mod use_case;
\ No newline at end of file
+pub mod statistics;
+pub mod time_keeper;
+pub mod transposition_table;
+pub mod tuning;
+mod two_player_alternate_game;
+mod two_player_imperfect_information_game;
+mod two_player_simultaneous_game;
+pub mod use_case;
+mod visualizer;
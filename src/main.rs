@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use game_ai::evaluation::{evaluate, game_seeds};
+use game_ai::transposition_table::{ReplacementPolicy, TranspositionTable};
+use game_ai::use_case::single_player_game_with_no_context::Acceptance;
+use game_ai::use_case::{single_player_game_with_context, single_player_game_with_no_context, two_player_alternate_game, two_player_imperfect_information_game, two_player_simultaneous_game};
+use rand::SeedableRng;
+
+const USAGE: &str = "usage: game_ai <game> <algorithm> [--option value]...
+
+games and algorithms:
+  maze         beam                        --beam-width (2) --beam-depth (5)
+               chokudai                    --beam-width (2) --beam-depth (5) --beam-number (2)
+               beam-time                   --time-limit
+  no-context   hill-climb                  --number (10000)
+               annealing                   --number (10000) --start-temp (500) --end-temp (10)
+               iterated-local-search       --time-limit --local-search-number (5) --kick-number (1) --acceptance (better-or-equal)
+  alternate    alpha-beta                  --depth (4)
+               iterative-deepening         --time-limit
+               iterative-deepening-tt      --time-limit --table-size (1048576)
+               mcts                        --playouts (1000) --c (1.0) --expand-threshold (10)
+               mcts-time                   --time-limit --c (1.0) --expand-threshold (10)
+               mcts-tt                     --playouts (1000) --c (1.0) --expand-threshold (10) --table-size (1048576)
+  simultaneous primitive-monte-carlo       --playouts (1000)
+               primitive-monte-carlo-time  --time-limit
+               duct                        --playouts (1000) --c (1.0) --expand-threshold (10)
+               duct-time                   --time-limit --c (1.0) --expand-threshold (10)
+  imperfect    pimc                        --determinizations (20) --depth (4)
+               pimc-time                   --time-limit --depth (4)
+               ismcts                      --playouts (1000) --c (1.0) --expand-threshold (10)
+               ismcts-time                 --time-limit --c (1.0) --expand-threshold (10)
+
+common options:
+  --games (100)    number of seeds to run
+  --seed (0)       seed from which the game seeds are made
+  --threads (1)    threads for single-player games (0 = all available)
+  --time-limit     seconds per game for single-player games (the search gets 90% of it), per move for two-player games
+
+single-player games print the score statistics; two-player games play against a random AI with both colours.
+the -tt algorithms keep one transposition table for all the games they play.
+--acceptance is one of better, better-or-equal, always and threshold:<n> (accept up to n below the best score).";
+
+struct Options {
+    values: HashMap<String, String>,
+}
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        // `--name value` の組を読む。
+        let mut values = HashMap::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let name = arg.strip_prefix("--").ok_or(format!("unexpected argument `{}`", arg))?;
+            let value = args.next().ok_or(format!("missing value for `{}`", arg))?;
+            values.insert(name.to_string(), value.clone());
+        }
+        Ok(Self { values })
+    }
+
+    fn get<T: std::str::FromStr>(&mut self, name: &str, default: Option<T>) -> Result<T, String> {
+        // 読んだ引数は取り除き、最後に残ったものを未知の引数として報告する。
+        match self.values.remove(name) {
+            Some(value) => value.parse::<T>().map_err(|_| format!("invalid value `{}` for `--{}`", value, name)),
+            None => default.ok_or(format!("`--{}` is required", name)),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self.values.keys().next() {
+            Some(name) => Err(format!("unknown option `--{}`", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let [game, algorithm, options @ ..] = args else {
+        return Err("missing game or algorithm".to_string());
+    };
+    let mut options = Options::parse(options)?;
+    let game_number = options.get("games", Some(100))?;
+    let seed = options.get("seed", Some(0))?;
+    // test_ai_score と同じ game_seeds で、seed からゲームごとのシードの列を作る。
+    let seeds = game_seeds(game_number, seed);
+    match game.as_str() {
+        "maze" | "no-context" => {
+            let play_game: Box<dyn Fn(u64, f64) -> isize + Sync> = match (game.as_str(), algorithm.as_str()) {
+                ("maze", "beam") => {
+                    let (beam_width, beam_depth) = (options.get("beam-width", Some(2))?, options.get("beam-depth", Some(5))?);
+                    Box::new(move |seed, _| single_player_game_with_context::play_game_with_beam_search(seed, beam_width, beam_depth))
+                }
+                ("maze", "chokudai") => {
+                    let (beam_width, beam_depth, beam_number) = (options.get("beam-width", Some(2))?, options.get("beam-depth", Some(5))?, options.get("beam-number", Some(2))?);
+                    Box::new(move |seed, _| single_player_game_with_context::play_game_with_chokudai_search(seed, beam_width, beam_depth, beam_number))
+                }
+                ("maze", "beam-time") => Box::new(single_player_game_with_context::play_game_with_time_threshold),
+                ("no-context", "hill-climb") => {
+                    let number = options.get("number", Some(10000))?;
+                    Box::new(move |seed, _| single_player_game_with_no_context::play_game_with_hill_climb(seed, number))
+                }
+                ("no-context", "annealing") => {
+                    let (number, start_temp, end_temp) = (options.get("number", Some(10000))?, options.get("start-temp", Some(500.0))?, options.get("end-temp", Some(10.0))?);
+                    Box::new(move |seed, _| single_player_game_with_no_context::play_game_with_simulated_annealing(seed, number, start_temp, end_temp))
+                }
+                ("no-context", "iterated-local-search") => {
+                    let (local_search_number, kick_number) = (options.get("local-search-number", Some(5))?, options.get("kick-number", Some(1))?);
+                    let acceptance = options.get("acceptance", Some(Acceptance::BetterOrEqual))?;
+                    Box::new(move |seed, time_threshold| single_player_game_with_no_context::play_game_with_time_threshold(seed, time_threshold, local_search_number, kick_number, acceptance))
+                }
+                _ => return Err(format!("unknown algorithm `{}` for `{}`", algorithm, game)),
+            };
+            // 時間で打ち切らない探索では time over を数えず、--time-limit は二人ゲームと同じく未知の引数として扱う。
+            let is_timed = matches!(algorithm.as_str(), "beam-time" | "iterated-local-search");
+            let time_threshold = if is_timed { options.get("time-limit", None)? } else { f64::INFINITY };
+            // 二人ゲームは一手ずつ対戦するので、--threads は一人ゲームでしか読まない。
+            let thread_number = options.get("threads", Some(1))?;
+            options.finish()?;
+            println!("{}", evaluate(&seeds, thread_number, time_threshold, &*play_game));
+        }
+        "alternate" => {
+            type Ai = Box<dyn Fn(&two_player_alternate_game::GameState, u64) -> Option<two_player_alternate_game::Action>>;
+            let ai: Ai = match algorithm.as_str() {
+                "alpha-beta" => {
+                    let depth = options.get("depth", Some(4))?;
+                    Box::new(move |state, _| two_player_alternate_game::alpha_beta_action(state, depth))
+                }
+                "iterative-deepening" => {
+                    let time_threshold = options.get("time-limit", None)?;
+                    Box::new(move |state, _| two_player_alternate_game::iterative_deepening_action(state, time_threshold))
+                }
+                "iterative-deepening-tt" => {
+                    let (time_threshold, table_size) = (options.get("time-limit", None)?, options.get("table-size", Some(1 << 20))?);
+                    let table = std::cell::RefCell::new(TranspositionTable::build(table_size, ReplacementPolicy::DepthPreferred));
+                    Box::new(move |state, _| two_player_alternate_game::iterative_deepening_action_with_transposition_table(state, time_threshold, &mut table.borrow_mut()))
+                }
+                "mcts" => {
+                    let (playout_number, c, expand_threshold) = (options.get("playouts", Some(1000))?, options.get("c", Some(1.0))?, options.get("expand-threshold", Some(10))?);
+                    Box::new(move |state, seed| two_player_alternate_game::mcts_action(state, playout_number, c, expand_threshold, seed))
+                }
+                "mcts-time" => {
+                    let (time_threshold, c, expand_threshold) = (options.get("time-limit", None)?, options.get("c", Some(1.0))?, options.get("expand-threshold", Some(10))?);
+                    Box::new(move |state, seed| two_player_alternate_game::mcts_action_with_time_threshold(state, c, expand_threshold, time_threshold, seed))
+                }
+                "mcts-tt" => {
+                    let (playout_number, c, expand_threshold) = (options.get("playouts", Some(1000))?, options.get("c", Some(1.0))?, options.get("expand-threshold", Some(10))?);
+                    let table_size = options.get("table-size", Some(1 << 20))?;
+                    let table = std::cell::RefCell::new(TranspositionTable::build(table_size, ReplacementPolicy::DepthPreferred));
+                    Box::new(move |state, seed| two_player_alternate_game::mcts_action_with_transposition_table(state, playout_number, c, expand_threshold, &mut table.borrow_mut(), seed))
+                }
+                _ => return Err(format!("unknown algorithm `{}` for `{}`", algorithm, game)),
+            };
+            options.finish()?;
+            let random_ai = |state: &two_player_alternate_game::GameState, seed: u64| two_player_alternate_game::random_action(state, &mut rand::rngs::StdRng::seed_from_u64(seed));
+            println!("{}", two_player_alternate_game::arena(&*ai, &random_ai, game_number, seed));
+        }
+        "simultaneous" => {
+            type Ai = Box<dyn Fn(&two_player_simultaneous_game::GameState, usize, u64) -> Option<two_player_simultaneous_game::Action>>;
+            let ai: Ai = match algorithm.as_str() {
+                "primitive-monte-carlo" => {
+                    let playout_number = options.get("playouts", Some(1000))?;
+                    Box::new(move |state, player_id, seed| two_player_simultaneous_game::primitive_monte_carlo_action(state, player_id, playout_number, seed))
+                }
+                "primitive-monte-carlo-time" => {
+                    let time_threshold = options.get("time-limit", None)?;
+                    Box::new(move |state, player_id, seed| two_player_simultaneous_game::primitive_monte_carlo_action_with_time_threshold(state, player_id, time_threshold, seed))
+                }
+                "duct" => {
+                    let (playout_number, c, expand_threshold) = (options.get("playouts", Some(1000))?, options.get("c", Some(1.0))?, options.get("expand-threshold", Some(10))?);
+                    Box::new(move |state, player_id, seed| two_player_simultaneous_game::duct_action(state, player_id, playout_number, c, expand_threshold, seed))
+                }
+                "duct-time" => {
+                    let (time_threshold, c, expand_threshold) = (options.get("time-limit", None)?, options.get("c", Some(1.0))?, options.get("expand-threshold", Some(10))?);
+                    Box::new(move |state, player_id, seed| two_player_simultaneous_game::duct_action_with_time_threshold(state, player_id, c, expand_threshold, time_threshold, seed))
+                }
+                _ => return Err(format!("unknown algorithm `{}` for `{}`", algorithm, game)),
+            };
+            options.finish()?;
+            let random_ai = |state: &two_player_simultaneous_game::GameState, player_id: usize, seed: u64| two_player_simultaneous_game::random_action(state, player_id, &mut rand::rngs::StdRng::seed_from_u64(seed));
+            println!("{}", two_player_simultaneous_game::arena(&*ai, &random_ai, game_number, seed));
+        }
+        "imperfect" => {
+            type Ai = Box<dyn Fn(&two_player_imperfect_information_game::GameState, u64) -> Option<two_player_alternate_game::Action>>;
+            let ai: Ai = match algorithm.as_str() {
+                "pimc" => {
+                    let (determinization_number, depth) = (options.get("determinizations", Some(20))?, options.get("depth", Some(4))?);
+                    Box::new(move |state, seed| two_player_imperfect_information_game::pimc_action(state, determinization_number, depth, seed))
+                }
+                "pimc-time" => {
+                    let (time_threshold, depth) = (options.get("time-limit", None)?, options.get("depth", Some(4))?);
+                    Box::new(move |state, seed| two_player_imperfect_information_game::pimc_action_with_time_threshold(state, depth, time_threshold, seed))
+                }
+                "ismcts" => {
+                    let (playout_number, c, expand_threshold) = (options.get("playouts", Some(1000))?, options.get("c", Some(1.0))?, options.get("expand-threshold", Some(10))?);
+                    Box::new(move |state, seed| two_player_imperfect_information_game::ismcts_action(state, playout_number, c, expand_threshold, seed))
+                }
+                "ismcts-time" => {
+                    let (time_threshold, c, expand_threshold) = (options.get("time-limit", None)?, options.get("c", Some(1.0))?, options.get("expand-threshold", Some(10))?);
+                    Box::new(move |state, seed| two_player_imperfect_information_game::ismcts_action_with_time_threshold(state, c, expand_threshold, time_threshold, seed))
+                }
+                _ => return Err(format!("unknown algorithm `{}` for `{}`", algorithm, game)),
+            };
+            options.finish()?;
+            let random_ai = |state: &two_player_imperfect_information_game::GameState, seed: u64| two_player_imperfect_information_game::random_action(state, &mut rand::rngs::StdRng::seed_from_u64(seed));
+            println!("{}", two_player_imperfect_information_game::arena(&*ai, &random_ai, game_number, seed));
+        }
+        _ => return Err(format!("unknown game `{}`", game)),
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    if let Err(error) = run(&args) {
+        eprintln!("error: {}\n\n{}", error, USAGE);
+        std::process::exit(2);
+    }
+}
@@ -0,0 +1,97 @@
+use rand::*;
+
+#[allow(unused)]
+#[derive(Clone, Copy, Debug)]
+pub struct Xorshift {
+    // xorshift64 の状態。0 になると 0 しか返さなくなるので、0 にはしない。
+    state: u64,
+}
+#[allow(unused)]
+impl Xorshift {
+    #[inline]
+    pub fn next_index(&mut self, n: usize) -> usize {
+        // 0 以上 n 未満の整数を返す。剰余の代わりに 128 bit の積の上位を使う。
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+
+    #[inline]
+    pub fn next_f64(&mut self) -> f64 {
+        // 0 以上 1 未満の実数を返す。
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+}
+impl RngCore for Xorshift {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+impl SeedableRng for Xorshift {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self::seed_from_u64(u64::from_le_bytes(seed))
+    }
+
+    fn seed_from_u64(seed: u64) -> Self {
+        // 近いシード値からも離れた状態を作るため、splitmix64 で混ぜる。
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        Self {
+            state: if z == 0 { 0x9e37_79b9_7f4a_7c15 } else { z },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_same_sequence() {
+        let mut a = Xorshift::seed_from_u64(42);
+        let mut b = Xorshift::seed_from_u64(42);
+        let mut c = Xorshift::seed_from_u64(43);
+        let a = (0..100).map(|_| a.next_u64()).collect::<Vec<_>>();
+        let b = (0..100).map(|_| b.next_u64()).collect::<Vec<_>>();
+        let c = (0..100).map(|_| c.next_u64()).collect::<Vec<_>>();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn next_index_and_next_f64_stay_in_range() {
+        let mut rng = Xorshift::seed_from_u64(0);
+        for n in [1, 2, 3, 7, 1200, usize::MAX] {
+            for _ in 0..1000 {
+                assert!(rng.next_index(n) < n);
+            }
+        }
+        for _ in 0..100000 {
+            let value = rng.next_f64();
+            assert!((0.0..1.0).contains(&value));
+        }
+    }
+}
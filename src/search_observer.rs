@@ -0,0 +1,70 @@
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchEvent {
+    // これまでの最良のスコアが更新された。
+    NewBest { iteration: usize, score: f64 },
+    // ビームサーチの層や反復深化の深さを一つ処理し終えた。score はその時点の最善の評価値。
+    LayerCompleted { depth: usize, score: f64 },
+    // 焼きなましの温度を変えた。
+    TemperatureStep { iteration: usize, temperature: f64 },
+    // 反復を一回終えた。score はその時点の評価値。
+    Iteration { iteration: usize, score: f64 },
+}
+
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchControl {
+    Continue,
+    // 探索を打ち切り、それまでの最良の結果を返させる。
+    Abort,
+}
+
+#[allow(unused)]
+pub trait SearchObserver {
+    // 探索は event ごとにこれを呼び、Abort が返されたら探索を打ち切る。
+    fn on_event(&mut self, event: &SearchEvent) -> SearchControl;
+}
+impl<F: FnMut(&SearchEvent) -> SearchControl> SearchObserver for F {
+    fn on_event(&mut self, event: &SearchEvent) -> SearchControl {
+        self(event)
+    }
+}
+
+#[allow(unused)]
+pub struct NoObserver;
+impl SearchObserver for NoObserver {
+    #[inline]
+    fn on_event(&mut self, _event: &SearchEvent) -> SearchControl {
+        SearchControl::Continue
+    }
+}
+
+#[allow(unused)]
+pub struct ScoreHistory {
+    // NewBest と LayerCompleted を受け取った時刻 (作成してからの秒数) とスコアの列。スコアの時間変化を描くのに使う。
+    start_time: std::time::Instant,
+    pub history: Vec<(f64, f64)>,
+}
+#[allow(unused)]
+impl ScoreHistory {
+    pub fn new() -> Self {
+        Self {
+            start_time: std::time::Instant::now(),
+            history: Vec::new(),
+        }
+    }
+}
+impl Default for ScoreHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl SearchObserver for ScoreHistory {
+    fn on_event(&mut self, event: &SearchEvent) -> SearchControl {
+        match *event {
+            SearchEvent::NewBest { score, .. } | SearchEvent::LayerCompleted { score, .. } => self.history.push((self.start_time.elapsed().as_secs_f64(), score)),
+            SearchEvent::TemperatureStep { .. } | SearchEvent::Iteration { .. } => {}
+        }
+        SearchControl::Continue
+    }
+}
@@ -0,0 +1,112 @@
+// 焼きなましの受理率の推移を、遷移の回数を何等分して記録するか。
+#[allow(unused)]
+pub const ACCEPTANCE_RATE_POINT_NUMBER: usize = 20;
+
+// 探索の *_with_observer に &mut で渡すと、探索がその中に数を書き込む。
+// 同じ SearchStatistics を続けて渡すと、それぞれの探索の分が足し合わされる。
+#[allow(unused)]
+#[derive(Clone, Debug, Default)]
+pub struct SearchStatistics {
+    // 合法手を列挙して子を作った節点の数と、作った状態の数。
+    pub expanded_node_number: usize,
+    pub generated_state_number: usize,
+    // 根から読んだ最大の深さ。
+    pub max_depth: usize,
+    // ビームサーチで最後まで処理したビームの層の数。遺伝的アルゴリズムでは作り終えた世代の数。
+    pub completed_layer_number: usize,
+    // 探索にかかった秒数の合計。
+    pub elapsed_time: f64,
+    // 焼きなましで、acceptance_window 回の遷移ごとに求めた受理率の推移。
+    pub acceptance_rates: Vec<f64>,
+    acceptance_window: usize,
+    tried: usize,
+    accepted: usize,
+}
+#[allow(unused)]
+impl SearchStatistics {
+    pub fn with_acceptance_window(acceptance_window: usize) -> Self {
+        // acceptance_window 回の遷移ごとに受理率を記録する SearchStatistics を作る。
+        Self {
+            acceptance_window: acceptance_window.max(1),
+            ..Self::default()
+        }
+    }
+
+    pub fn set_acceptance_window_if_unset(&mut self, acceptance_window: usize) {
+        // 呼び出し側が with_acceptance_window で区間を決めていなければ、焼きなましの既定の区間を使う。
+        if self.acceptance_window == 0 {
+            self.acceptance_window = acceptance_window.max(1);
+        }
+    }
+
+    #[inline]
+    pub fn record_depth(&mut self, depth: usize) {
+        self.max_depth = self.max_depth.max(depth);
+    }
+
+    #[inline]
+    pub fn record_acceptance(&mut self, is_accepted: bool) {
+        if self.acceptance_window == 0 {
+            return;
+        }
+        self.tried += 1;
+        self.accepted += is_accepted as usize;
+        if self.tried == self.acceptance_window {
+            self.acceptance_rates.push(self.accepted as f64 / self.tried as f64);
+            self.tried = 0;
+            self.accepted = 0;
+        }
+    }
+
+    pub fn finish(&mut self, start_time: std::time::Instant) {
+        // 経過時間を足し、途中までの受理率の区間があればそれも記録する。
+        self.elapsed_time += start_time.elapsed().as_secs_f64();
+        if self.tried > 0 {
+            self.acceptance_rates.push(self.accepted as f64 / self.tried as f64);
+            self.tried = 0;
+            self.accepted = 0;
+        }
+    }
+}
+impl std::fmt::Display for SearchStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "expanded: {}, generated: {}, depth: {}, layers: {}, time: {:.3}s",
+            self.expanded_node_number, self.generated_state_number, self.max_depth, self.completed_layer_number, self.elapsed_time
+        )?;
+        if !self.acceptance_rates.is_empty() {
+            let rates = self.acceptance_rates.iter().map(|rate| format!("{:.3}", rate)).collect::<Vec<_>>();
+            write!(f, ", acceptance rates: [{}]", rates.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acceptance_rates_follow_the_window() {
+        // 4 回ごとの受理率を並べ、finish で最後の途中までの区間も記録する。
+        let mut statistics = SearchStatistics::with_acceptance_window(4);
+        statistics.set_acceptance_window_if_unset(2);
+        for is_accepted in [true, false, true, true, false, false, false, true, true, false] {
+            statistics.record_acceptance(is_accepted);
+        }
+        assert_eq!(statistics.acceptance_rates, vec![0.75, 0.25]);
+        statistics.finish(std::time::Instant::now());
+        assert_eq!(statistics.acceptance_rates, vec![0.75, 0.25, 0.5]);
+        statistics.finish(std::time::Instant::now());
+        assert_eq!(statistics.acceptance_rates.len(), 3);
+    }
+
+    #[test]
+    fn acceptance_is_not_recorded_without_a_window() {
+        let mut statistics = SearchStatistics::default();
+        statistics.record_acceptance(true);
+        statistics.finish(std::time::Instant::now());
+        assert!(statistics.acceptance_rates.is_empty());
+    }
+}
@@ -1,6 +1,12 @@
 use rand::*;
 
+use crate::evaluation::{evaluate, game_seeds, EvaluationResult};
+use crate::game_record::GameRecord;
+use crate::search_observer::{NoObserver, SearchControl, SearchEvent, SearchObserver};
+use crate::search_statistics::SearchStatistics;
+use crate::statistics::{PairedComparison, ScoreSummary};
 use crate::time_keeper::TimeKeeper;
+use crate::tuning::{tune, Parameter, ParameterRange, TuningMethod, TuningResult};
 
 #[allow(unused)]
 #[derive(Clone, Copy)]
@@ -39,6 +45,17 @@ impl Board {
 enum Action {
     // player がし得る action 全体。
 }
+#[allow(unused)]
+impl Action {
+    fn to_record(self) -> String {
+        // GameRecord に書く action の文字列を返す関数。
+        match self {}
+    }
+
+    fn from_record(text: &str) -> Option<Self> {
+        ACTIONS.into_iter().find(|action| action.to_record() == text)
+    }
+}
 // Action のなかで player が取り得る action　全体。
 #[allow(unused)]
 const ACTIONS: [Action; 0] = [];
@@ -63,9 +80,9 @@ impl GameState {
         let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
         let end_turn = rng.gen::<usize>();
         Self {
-            board: Board::new(0),
+            board: Board::new(rng.gen::<u64>()),
             turn: 0,
-            player: Player::new(0),
+            player: Player::new(rng.gen::<u64>()),
             game_score: 0,
             evaliated_score: 0,
             first_action: None,
@@ -103,6 +120,17 @@ impl GameState {
     fn evaliated_score(&mut self) {
         // self.evaluated_score を更新す処理をする関数。
     }
+
+    fn board_lines(&self) -> Vec<String> {
+        // GameRecord に書く初期盤面を一行ずつの文字列にして返す関数。
+        Vec::new()
+    }
+
+    fn from_board_lines(lines: &[String], end_turn: usize) -> Result<Self, String> {
+        // board_lines で書いた盤面から、end_turn ターンで終わる GameState を作る関数。player がちょうど一人でない盤面は Err にする。
+        // 作れないうちは replay_game_record が誤った盤面で再生しないよう、Err を返しておく。
+        Err("from_board_lines is not implemented".to_string())
+    }
 }
 
 impl PartialEq for GameState {
@@ -126,8 +154,7 @@ impl Ord for GameState {
 
 // ここから先は、action を決定する方法を定める関数を作る。
 #[allow(unused)]
-fn random_action(state: &GameState) -> Option<Action> {
-    let mut rng = rand::thread_rng();
+fn random_action(state: &GameState, rng: &mut rand::rngs::StdRng) -> Option<Action> {
     let legal_actions = state.legal_actions();
     if legal_actions.is_empty() {
         return None;
@@ -151,12 +178,19 @@ fn greedy_action(state: &GameState) -> Option<Action> {
 }
 #[allow(unused)]
 fn beam_search_action(state: &GameState, beam_width: usize, beam_depth: usize) -> Option<Action> {
+    beam_search_action_with_observer(state, beam_width, beam_depth, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn beam_search_action_with_observer(state: &GameState, beam_width: usize, beam_depth: usize, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // 層を一つ処理するたびに observer に LayerCompleted を送り、Abort が返されたらそこまでの最善手を返す。
+    let start_time = std::time::Instant::now();
     let mut now_beam = std::collections::BinaryHeap::new();
     let mut best_state = state.clone();
     now_beam.push(*state);
     for t in 0..beam_depth {
         let mut next_beam = std::collections::BinaryHeap::new();
         for now_state in now_beam.iter().take(beam_width) {
+            statistics.expanded_node_number += 1;
             for &action in now_state.legal_actions().iter() {
                 let mut next_state = now_state.clone();
                 next_state.advance(action);
@@ -165,27 +199,39 @@ fn beam_search_action(state: &GameState, beam_width: usize, beam_depth: usize) -
                     next_state.first_action = Some(action);
                 }
                 next_beam.push(next_state);
+                statistics.generated_state_number += 1;
             }
         }
         now_beam = next_beam;
+        statistics.completed_layer_number += 1;
+        statistics.record_depth(t + 1);
         best_state = *now_beam.peek().unwrap();
-        if best_state.is_done() {
+        let control = observer.on_event(&SearchEvent::LayerCompleted { depth: t + 1, score: best_state.evaliated_score as f64 });
+        if best_state.is_done() || control == SearchControl::Abort {
             break;
         }
     }
+    statistics.finish(start_time);
     best_state.first_action
 }
 #[allow(unused)]
 fn chokudai_search_action(state: &GameState, beam_width: usize, beam_depth: usize, beam_number: usize) -> Option<Action> {
+    chokudai_search_action_with_observer(state, beam_width, beam_depth, beam_number, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn chokudai_search_action_with_observer(state: &GameState, beam_width: usize, beam_depth: usize, beam_number: usize, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // 全ての層を一巡するたびに observer に Iteration を送り、Abort が返されたらそこまでの最善手を返す。
+    let start_time = std::time::Instant::now();
     let mut beams = vec![std::collections::BinaryHeap::new(); beam_depth + 1];
     beams[0].push(*state);
-    for _ in 0..beam_number {
+    for iteration in 0..beam_number {
         for t in 0..beam_depth {
             let mut next_beam = std::mem::take(&mut beams[t + 1]);
             for now_state in beams[t].iter().take(beam_width) {
                 if now_state.is_done() {
                     break;
                 }
+                statistics.expanded_node_number += 1;
                 for &action in now_state.legal_actions().iter() {
                     let mut next_state = now_state.clone();
                     next_state.advance(action);
@@ -194,13 +240,33 @@ fn chokudai_search_action(state: &GameState, beam_width: usize, beam_depth: usiz
                         next_state.first_action = Some(action);
                     }
                     next_beam.push(next_state);
+                    statistics.generated_state_number += 1;
                 }
             }
             std::mem::swap(&mut beams[t + 1], &mut next_beam);
+            statistics.completed_layer_number += 1;
+        }
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: chokudai_search_best_score(&beams) }) == SearchControl::Abort {
+            break;
         }
     }
-    for beam in beams.iter().rev() {
+    chokudai_search_best_action(&beams, statistics, start_time)
+}
+#[allow(unused)]
+fn chokudai_search_best_score(beams: &[std::collections::BinaryHeap<GameState>]) -> f64 {
+    // 最も深い空でないビームの先頭の評価値を返す。
+    match beams.iter().rev().find_map(|beam| beam.peek()) {
+        Some(state) => state.evaliated_score as f64,
+        None => -INF as f64,
+    }
+}
+#[allow(unused)]
+fn chokudai_search_best_action(beams: &[std::collections::BinaryHeap<GameState>], statistics: &mut SearchStatistics, start_time: std::time::Instant) -> Option<Action> {
+    // 最も深い空でないビームの先頭の first_action を返す。
+    statistics.finish(start_time);
+    for (t, beam) in beams.iter().enumerate().rev() {
         if !beam.is_empty() {
+            statistics.record_depth(t);
             return beam.peek().unwrap().first_action;
         }
     }
@@ -208,16 +274,22 @@ fn chokudai_search_action(state: &GameState, beam_width: usize, beam_depth: usiz
 }
 #[allow(unused)]
 fn beam_search_action_with_time_threshold(state: &GameState, beam_width: usize, time_threshold: f64) -> Option<Action> {
+    beam_search_action_with_time_threshold_and_observer(state, beam_width, time_threshold, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn beam_search_action_with_time_threshold_and_observer(state: &GameState, beam_width: usize, time_threshold: f64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
     let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
     let mut now_beam = std::collections::BinaryHeap::new();
     let mut best_state = state.clone();
     now_beam.push(*state);
-    for t in 0.. {
+    'search: for t in 0.. {
         let mut next_beam = std::collections::BinaryHeap::new();
         for now_state in now_beam.iter().take(beam_width) {
             if time_keeper.is_time_over() {
-                return best_state.first_action;
+                break 'search;
             }
+            statistics.expanded_node_number += 1;
             for &action in now_state.legal_actions().iter() {
                 let mut next_state = now_state.clone();
                 next_state.advance(action);
@@ -226,28 +298,39 @@ fn beam_search_action_with_time_threshold(state: &GameState, beam_width: usize,
                     next_state.first_action = Some(action);
                 }
                 next_beam.push(next_state);
+                statistics.generated_state_number += 1;
             }
         }
         now_beam = next_beam;
+        statistics.completed_layer_number += 1;
+        statistics.record_depth(t + 1);
         best_state = *now_beam.peek().unwrap();
-        if best_state.is_done() {
+        let control = observer.on_event(&SearchEvent::LayerCompleted { depth: t + 1, score: best_state.evaliated_score as f64 });
+        if best_state.is_done() || control == SearchControl::Abort {
             break;
         }
     }
+    statistics.finish(start_time);
     best_state.first_action
 }
 #[allow(unused)]
 fn chokudai_search_action_with_time_threshold(state: &GameState, beam_width: usize, beam_depth: usize, time_threshold: f64) -> Option<Action> {
+    chokudai_search_action_with_time_threshold_and_observer(state, beam_width, beam_depth, time_threshold, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn chokudai_search_action_with_time_threshold_and_observer(state: &GameState, beam_width: usize, beam_depth: usize, time_threshold: f64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
     let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
     let mut beams = vec![std::collections::BinaryHeap::new(); beam_depth + 1];
     beams[0].push(*state);
-    for _ in 0.. {
+    for iteration in 0.. {
         for t in 0..beam_depth {
             let mut next_beam = std::mem::take(&mut beams[t + 1]);
             for now_state in beams[t].iter().take(beam_width) {
                 if now_state.is_done() {
                     break;
                 }
+                statistics.expanded_node_number += 1;
                 for &action in now_state.legal_actions().iter() {
                     let mut next_state = now_state.clone();
                     next_state.advance(action);
@@ -256,27 +339,28 @@ fn chokudai_search_action_with_time_threshold(state: &GameState, beam_width: usi
                         next_state.first_action = Some(action);
                     }
                     next_beam.push(next_state);
+                    statistics.generated_state_number += 1;
                 }
             }
             beams[t + 1] = next_beam;
+            statistics.completed_layer_number += 1;
         }
-        if time_keeper.is_time_over() {
+        if time_keeper.is_time_over() || observer.on_event(&SearchEvent::Iteration { iteration, score: chokudai_search_best_score(&beams) }) == SearchControl::Abort {
             break;
         }
     }
-    for beam in beams.iter().rev() {
-        if !beam.is_empty() {
-            return beam.peek().unwrap().first_action;
-        }
-    }
-    None
+    chokudai_search_best_action(&beams, statistics, start_time)
 }
 
 #[allow(unused)]
 pub fn play_game(seed: u64) -> isize {
+    play_game_with_beam_search(seed, 2, 5)
+}
+#[allow(unused)]
+pub fn play_game_with_beam_search(seed: u64, beam_width: usize, beam_depth: usize) -> isize {
     let mut state = GameState::new(seed);
     while !state.is_done() {
-        if let Some(action) = beam_search_action(&state, 2, 5) {
+        if let Some(action) = beam_search_action(&state, beam_width, beam_depth) {
             state.advance(action);
         } else {
             return -INF;
@@ -285,11 +369,95 @@ pub fn play_game(seed: u64) -> isize {
     state.game_score
 }
 #[allow(unused)]
-pub fn test_ai_score(game_number: usize, seed: u64) -> f64 {
-    let mut rngs: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
-    let mut score_sum = 0;
-    for _ in 0..game_number {
-        score_sum += play_game(rngs.gen::<u64>()) as i128;
+pub fn play_game_with_chokudai_search(seed: u64, beam_width: usize, beam_depth: usize, beam_number: usize) -> isize {
+    let mut state = GameState::new(seed);
+    while !state.is_done() {
+        if let Some(action) = chokudai_search_action(&state, beam_width, beam_depth, beam_number) {
+            state.advance(action);
+        } else {
+            return -INF;
+        }
     }
-    score_sum as f64 / game_number as f64
-}
\ No newline at end of file
+    state.game_score
+}
+#[allow(unused)]
+pub fn play_game_with_time_threshold(seed: u64, time_threshold: f64) -> isize {
+    // 一ゲームに time_threshold 秒を使い、残りのターンに等分して beam_search_action_with_time_threshold で進める。
+    let mut state = GameState::new(seed);
+    let turn_time_threshold = time_threshold / (state.end_turn - state.turn).max(1) as f64;
+    while !state.is_done() {
+        if let Some(action) = beam_search_action_with_time_threshold(&state, 2, turn_time_threshold) {
+            state.advance(action);
+        } else {
+            return -INF;
+        }
+    }
+    state.game_score
+}
+#[allow(unused)]
+pub fn play_game_record(seed: u64) -> GameRecord {
+    play_game_record_with(seed, &|state| beam_search_action(state, 2, 5))
+}
+#[allow(unused)]
+fn play_game_record_with(seed: u64, choose_action: &dyn Fn(&GameState) -> Option<Action>) -> GameRecord {
+    // choose_action でゲームを進め、初期盤面と各ターンの action とスコアを記録する。action が無くなったらそこで記録を終える。
+    let mut state = GameState::new(seed);
+    let mut record = GameRecord::new(seed, state.board_lines());
+    while !state.is_done() {
+        let Some(action) = choose_action(&state) else {
+            break;
+        };
+        state.advance(action);
+        record.push(action.to_record(), state.game_score);
+    }
+    record
+}
+#[allow(unused)]
+pub fn replay_game_record(record: &GameRecord) -> Result<isize, String> {
+    // record の初期盤面から action を advance で順に適用し、各ターンのスコアが記録と一致することを確かめて最終スコアを返す。
+    // 終了ターンは記録の長さではなく、record.seed で作ったゲームの値を使う。
+    let end_turn = GameState::new(record.seed).end_turn;
+    let mut state = GameState::from_board_lines(&record.initial_board, end_turn)?;
+    for (turn, (action, &score)) in record.actions.iter().zip(record.scores.iter()).enumerate() {
+        if state.is_done() {
+            return Err(format!("turn {}: game already ended after {} turns", turn, end_turn));
+        }
+        let action = Action::from_record(action).ok_or(format!("turn {}: unknown action `{}`", turn, action))?;
+        if !state.legal_actions().into_iter().any(|legal_action| legal_action.to_record() == action.to_record()) {
+            return Err(format!("turn {}: illegal action `{}`", turn, action.to_record()));
+        }
+        state.advance(action);
+        if state.game_score != score {
+            return Err(format!("turn {}: recorded score {} but replayed score {}", turn, score, state.game_score));
+        }
+    }
+    Ok(state.game_score)
+}
+#[allow(unused)]
+pub fn test_ai_score(game_number: usize, seed: u64) -> ScoreSummary {
+    test_ai_score_with(&play_game, game_number, seed)
+}
+#[allow(unused)]
+pub fn test_ai_score_with(play_game: &dyn Fn(u64) -> isize, game_number: usize, seed: u64) -> ScoreSummary {
+    // play_game を game_seeds で seed から作ったシードの列で動かす。
+    ScoreSummary::build(game_seeds(game_number, seed).into_iter().map(|seed| (seed, play_game(seed))).collect())
+}
+#[allow(unused)]
+pub fn test_ai_score_in_parallel(game_number: usize, seed: u64, thread_number: usize, time_threshold: f64) -> EvaluationResult {
+    evaluate(&game_seeds(game_number, seed), thread_number, time_threshold, &play_game_with_time_threshold)
+}
+#[allow(unused)]
+pub fn compare_ai_score(play_game: &dyn Fn(u64) -> isize, other_play_game: &dyn Fn(u64) -> isize, game_number: usize, seed: u64) -> PairedComparison {
+    // 二つの play_game を同じシードの列で動かし、スコアの差を対応のある比較で評価する。
+    PairedComparison::build(game_seeds(game_number, seed).into_iter().map(|seed| (seed, play_game(seed), other_play_game(seed))).collect())
+}
+#[allow(unused)]
+pub fn tune_beam_search(method: TuningMethod, game_number: usize, seed: u64) -> Result<TuningResult, String> {
+    // beam_search_action の beam_width と beam_depth を、test_ai_score の平均スコアが高くなるように選ぶ。
+    let parameters = [Parameter::build("beam_width", ParameterRange::Int(1, 10))?, Parameter::build("beam_depth", ParameterRange::Int(1, 10))?];
+    let objective = |configuration: &[f64], game_number: usize| {
+        let (beam_width, beam_depth) = (configuration[0] as usize, configuration[1] as usize);
+        test_ai_score_with(&|seed| play_game_with_beam_search(seed, beam_width, beam_depth), game_number, seed).mean
+    };
+    tune(&parameters, &objective, method, game_number)
+}
@@ -1,5 +1,13 @@
 use rand::*;
 
+use crate::evaluation::{evaluate, game_seeds, EvaluationResult};
+use crate::random::Xorshift;
+use crate::search_observer::{NoObserver, SearchControl, SearchEvent, SearchObserver};
+use crate::search_statistics::{SearchStatistics, ACCEPTANCE_RATE_POINT_NUMBER};
+use crate::statistics::{PairedComparison, ScoreSummary};
+use crate::time_keeper::TimeKeeper;
+use crate::tuning::{tune, Parameter, ParameterRange, TuningMethod, TuningResult};
+
 #[allow(unused)]
 #[derive(Clone, Copy)]
 struct Player {
@@ -16,9 +24,26 @@ impl Player {
     }
 
     fn transition(&mut self, seed: u64) {
-        let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+        let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
         // player の情報を一部更新する関数。
     }
+
+    fn transition_with(&mut self, neighborhood: Neighborhood, seed: u64) {
+        let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+        // neighborhood の種類に応じて player の情報を一部更新する関数。
+        match neighborhood {}
+    }
+
+    fn kick(&mut self, seed: u64) {
+        let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+        // transition より大きく player の情報を変化させる関数。iterated_local_search で用いる。
+    }
+
+    fn crossover(&self, other: &Player, seed: u64) -> Self {
+        let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+        // self と other の情報を混ぜ合わせた player を返す関数。
+        *self
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -48,6 +73,27 @@ const ACTIONS: [Action; 0] = [];
 #[allow(unused)]
 const INF: isize = std::isize::MAX / 2;
 
+#[allow(unused)]
+#[derive(Clone, Copy)]
+enum Neighborhood {
+    // player の状態を遷移させる近傍の種類全体。
+}
+#[allow(unused)]
+impl Neighborhood {
+    fn name(&self) -> &'static str {
+        // 統計の表示に用いる近傍の名前を返す関数。
+        match *self {}
+    }
+}
+// adaptive_simulated_annealing が選択に用いる近傍全体。
+#[allow(unused)]
+const NEIGHBORHOODS: [Neighborhood; 0] = [];
+// 近傍の直近の成功率を更新するときの減衰率と、成功率が低い近傍にも残す選択の重み。
+#[allow(unused)]
+const NEIGHBORHOOD_DECAY: f64 = 0.95;
+#[allow(unused)]
+const MIN_NEIGHBORHOOD_WEIGHT: f64 = 0.05;
+
 #[derive(Clone, Copy)]
 #[allow(unused)]
 struct GameState {
@@ -64,8 +110,8 @@ impl GameState {
     pub fn new(seed: u64) -> Self {
         // GameState をシード値を用いて作成する。
         let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
-        let board = Board::new(0);
-        let player = Player::new(0);
+        let board = Board::new(rng.gen::<u64>());
+        let player = Player::new(rng.gen::<u64>());
         let end_turn = rng.gen::<usize>();
         Self {
             board: board,
@@ -90,15 +136,13 @@ impl GameState {
         }
     }
 
-    pub fn init(&mut self) {
+    pub fn init(&mut self, rng: &mut Xorshift) {
         // player の状態をランダムに初期化する。
-        let mut rng = rand::thread_rng();
         self.player = Player::new(rng.gen::<u64>());
     }
 
-    pub fn transition(&mut self) {
+    pub fn transition(&mut self, rng: &mut Xorshift) {
         // player の状態を一つ遷移させ、盤面を初期化する。
-        let mut rng = rand::thread_rng();
         self.board = self.initial_board.clone();
         self.turn = 0;
         self.player.transition(rng.gen::<u64>());
@@ -106,6 +150,49 @@ impl GameState {
         self.evaliated_score = 0;
     }
 
+    pub fn transition_with_delta(&mut self, rng: &mut Xorshift) -> Option<isize> {
+        // player の状態を一つ遷移させ、game を最初から再現せずにスコアを求められる場合はそれを返す。
+        // 差分計算を実装しない場合や差分計算ができない場合は、transition と同じく盤面を初期化して None を返す。
+        // その場合は呼び出し側で get_score を呼ぶ。
+        self.transition(rng);
+        None
+    }
+
+    pub fn kick(&mut self, rng: &mut Xorshift) {
+        // transition より大きく player の状態を変化させ、盤面を初期化する。
+        self.board = self.initial_board;
+        self.turn = 0;
+        self.player.kick(rng.gen::<u64>());
+        self.game_score = 0;
+        self.evaliated_score = 0;
+    }
+
+    pub fn transition_with(&mut self, neighborhood: Neighborhood, rng: &mut Xorshift) {
+        // player の状態を neighborhood で一つ遷移させ、盤面を初期化する。
+        self.board = self.initial_board;
+        self.turn = 0;
+        self.player.transition_with(neighborhood, rng.gen::<u64>());
+        self.game_score = 0;
+        self.evaliated_score = 0;
+    }
+
+    pub fn transition_with_neighborhood_and_delta(&mut self, neighborhood: Neighborhood, rng: &mut Xorshift) -> Option<isize> {
+        // transition_with_delta と同じだが、player の状態を neighborhood で遷移させる。adaptive_simulated_annealing で用いる。
+        self.transition_with(neighborhood, rng);
+        None
+    }
+
+    pub fn crossover(&self, other: &GameState, rng: &mut Xorshift) -> Self {
+        // self と other の player を交叉させた状態を作り、盤面を初期化する。
+        let mut state = *self;
+        state.board = self.initial_board;
+        state.turn = 0;
+        state.player = self.player.crossover(&other.player, rng.gen::<u64>());
+        state.game_score = 0;
+        state.evaliated_score = 0;
+        state
+    }
+
     fn is_done(&self) -> bool {
         // game が終了しているかどうかを返す。
         self.turn == self.end_turn
@@ -123,56 +210,659 @@ impl GameState {
     }
 }
 #[allow(unused)]
-fn random(mut state: GameState) -> GameState {
-    state.init();
+fn random(mut state: GameState, seed: u64) -> GameState {
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    state.init(&mut rng);
+    state
+}
+#[allow(unused)]
+fn hill_climb(state: GameState, number: usize, seed: u64) -> GameState {
+    hill_climb_with_observer(state, number, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn hill_climb_with_observer(mut state: GameState, number: usize, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    let start_time = std::time::Instant::now();
+    statistics.set_acceptance_window_if_unset(number / ACCEPTANCE_RATE_POINT_NUMBER);
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    state.init(&mut rng);
+    let state = local_search_with_observer(state, number, &mut rng, statistics, observer).0;
+    statistics.finish(start_time);
     state
 }
 #[allow(unused)]
-fn hill_climb(mut state: GameState, number: usize) -> GameState {
-    state.init();
+fn local_search(state: GameState, number: usize, rng: &mut Xorshift) -> (GameState, isize) {
+    local_search_with_observer(state, number, rng, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn local_search_with_observer(mut state: GameState, number: usize, rng: &mut Xorshift, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> (GameState, isize) {
+    // state から始めて、スコアが改善する遷移だけを受理することを number 回繰り返す。
+    // 改善するたびに observer に NewBest を、遷移を一回試すたびに Iteration を送り、Abort が返されたらそこで打ち切る。
     let mut best_score = state.get_score();
-    for _ in 0..number {
+    for iteration in 0..number {
         let mut now_state = state.clone();
-        now_state.transition();
-        let now_score = now_state.get_score();
+        let now_score = match now_state.transition_with_delta(rng) {
+            Some(score) => score,
+            None => now_state.get_score(),
+        };
+        statistics.generated_state_number += 1;
+        statistics.record_acceptance(now_score > best_score);
         if now_score > best_score {
             best_score = now_score;
             std::mem::swap(&mut state, &mut now_state);
+            if observer.on_event(&SearchEvent::NewBest { iteration, score: best_score as f64 }) == SearchControl::Abort {
+                break;
+            }
+        }
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: best_score as f64 }) == SearchControl::Abort {
+            break;
         }
     }
-    state
+    (state, best_score)
+}
+#[allow(unused)]
+fn simulated_annealing(state: GameState, number: usize, start_temp: f64, end_temp: f64, seed: u64) -> GameState {
+    simulated_annealing_with_observer(state, number, start_temp, end_temp, seed, &mut SearchStatistics::default(), &mut NoObserver)
 }
 #[allow(unused)]
-fn simulated_annealing(mut state: GameState, number: usize, start_temp: f64, end_temp: f64) -> GameState {
-    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(0);
-    state.init();
+fn simulated_annealing_with_observer(mut state: GameState, number: usize, start_temp: f64, end_temp: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    // 遷移を一回試すたびに observer に TemperatureStep と Iteration を、これまでの最高スコアを更新するたびに NewBest を送る。
+    // Abort が返されたらそこで打ち切り、その時点の状態を返す。
+    let start_time = std::time::Instant::now();
+    statistics.set_acceptance_window_if_unset(number / ACCEPTANCE_RATE_POINT_NUMBER);
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    state.init(&mut rng);
     let mut best_score = state.get_score();
+    let mut max_score = best_score;
     for i in 0..number {
-        let mut now_state = state.clone();
-        now_state.transition();
-        let now_score = now_state.get_score();
         let temp = start_temp + (end_temp - start_temp) * (i as f64 / number as f64);
+        if observer.on_event(&SearchEvent::TemperatureStep { iteration: i, temperature: temp }) == SearchControl::Abort {
+            break;
+        }
+        let mut now_state = state.clone();
+        let now_score = match now_state.transition_with_delta(&mut rng) {
+            Some(score) => score,
+            None => now_state.get_score(),
+        };
+        statistics.generated_state_number += 1;
         let probability = ((now_score - best_score) as f64 / temp).exp();
-        if now_score > best_score || probability > rng.gen::<u8>() as f64 / std::u8::MAX as f64 {
+        let is_accepted = now_score > best_score || probability > rng.next_f64();
+        statistics.record_acceptance(is_accepted);
+        if is_accepted {
             best_score = now_score;
             std::mem::swap(&mut state, &mut now_state);
         }
+        if best_score > max_score {
+            max_score = best_score;
+            if observer.on_event(&SearchEvent::NewBest { iteration: i, score: max_score as f64 }) == SearchControl::Abort {
+                break;
+            }
+        }
+        if observer.on_event(&SearchEvent::Iteration { iteration: i, score: best_score as f64 }) == SearchControl::Abort {
+            break;
+        }
     }
+    statistics.finish(start_time);
     state
 }
+#[allow(unused)]
+fn temperature_for_acceptance(deltas: &[f64], acceptance: f64) -> f64 {
+    // 悪化量 deltas の遷移を受理する確率の平均が acceptance になる温度を、温度の対数の二分探索で求める。
+    let acceptance = acceptance.clamp(1e-9, 1.0 - 1e-9);
+    let average_acceptance = |temp: f64| deltas.iter().map(|delta| (-delta / temp).exp()).sum::<f64>() / deltas.len() as f64;
+    let (mut low, mut high) = (1e-9f64.ln(), 1e9f64.ln());
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+        if average_acceptance(middle.exp()) < acceptance {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    ((low + high) / 2.0).exp()
+}
+#[allow(unused)]
+fn estimate_temperatures(mut state: GameState, sample_number: usize, start_acceptance: f64, end_acceptance: f64, seed: u64) -> (f64, f64) {
+    // random な状態から transition を sample_number 回続けてスコアの変化を集め、
+    // スコアが悪化する遷移の受理率の平均が、最初は start_acceptance、最後は end_acceptance になる (start_temp, end_temp) を返す。
+    // 悪化する遷移が一度もなければ、どの温度でも同じなので (1.0, 1.0) を返す。
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    state.init(&mut rng);
+    let mut now_score = state.get_score();
+    let mut deltas = Vec::with_capacity(sample_number);
+    for _ in 0..sample_number {
+        let next_score = match state.transition_with_delta(&mut rng) {
+            Some(score) => score,
+            None => state.get_score(),
+        };
+        if next_score < now_score {
+            deltas.push((now_score - next_score) as f64);
+        }
+        now_score = next_score;
+    }
+    if deltas.is_empty() {
+        return (1.0, 1.0);
+    }
+    (temperature_for_acceptance(&deltas, start_acceptance), temperature_for_acceptance(&deltas, end_acceptance))
+}
+#[allow(unused)]
+fn simulated_annealing_with_estimated_temperatures(state: GameState, number: usize, start_acceptance: f64, end_acceptance: f64, seed: u64) -> GameState {
+    // estimate_temperatures で求めた温度で simulated_annealing を行う。
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let (start_temp, end_temp) = estimate_temperatures(state, (number / 10).max(100), start_acceptance, end_acceptance, rng.gen::<u64>());
+    simulated_annealing(state, number, start_temp, end_temp, rng.gen::<u64>())
+}
+
+#[allow(unused)]
+#[derive(Clone, Copy)]
+struct NeighborhoodStatistics {
+    // adaptive_simulated_annealing で、近傍一つごとの試行回数と受理回数を記録する。
+    neighborhood: Neighborhood,
+    tried: usize,
+    accepted: usize,
+    improved: usize,
+    recent_success_rate: f64,
+}
+#[allow(unused)]
+impl NeighborhoodStatistics {
+    fn new(neighborhood: Neighborhood) -> Self {
+        // 最初はどの近傍も一度は選ばれるよう、直近の成功率を 1 から始める。
+        Self {
+            neighborhood,
+            tried: 0,
+            accepted: 0,
+            improved: 0,
+            recent_success_rate: 1.0,
+        }
+    }
+
+    fn weight(&self) -> f64 {
+        self.recent_success_rate.max(MIN_NEIGHBORHOOD_WEIGHT)
+    }
+
+    fn record(&mut self, is_accepted: bool, is_improved: bool) {
+        self.tried += 1;
+        self.accepted += is_accepted as usize;
+        self.improved += is_improved as usize;
+        let success = if is_accepted { 1.0 } else { 0.0 };
+        self.recent_success_rate = NEIGHBORHOOD_DECAY * self.recent_success_rate + (1.0 - NEIGHBORHOOD_DECAY) * success;
+    }
+
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.tried == 0 {
+            return 0.0;
+        }
+        self.accepted as f64 / self.tried as f64
+    }
+}
+impl std::fmt::Display for NeighborhoodStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: tried {}, accepted {} ({:.1}%), improved {}",
+            self.neighborhood.name(),
+            self.tried,
+            self.accepted,
+            self.acceptance_rate() * 100.0,
+            self.improved
+        )
+    }
+}
+#[allow(unused)]
+fn select_neighborhood(statistics: &[NeighborhoodStatistics], rng: &mut Xorshift) -> usize {
+    // 直近の成功率に比例した確率で近傍を選ぶ。
+    let total_weight = statistics.iter().map(|statistic| statistic.weight()).sum::<f64>();
+    let mut r = rng.next_f64() * total_weight;
+    for (k, statistic) in statistics.iter().enumerate() {
+        if r < statistic.weight() {
+            return k;
+        }
+        r -= statistic.weight();
+    }
+    statistics.len() - 1
+}
+#[allow(unused)]
+fn adaptive_simulated_annealing(state: GameState, number: usize, start_temp: f64, end_temp: f64, seed: u64) -> (GameState, Vec<NeighborhoodStatistics>) {
+    adaptive_simulated_annealing_with_observer(state, number, start_temp, end_temp, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn adaptive_simulated_annealing_with_observer(mut state: GameState, number: usize, start_temp: f64, end_temp: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> (GameState, Vec<NeighborhoodStatistics>) {
+    // NEIGHBORHOODS の中から、直近の受理率が高い近傍ほど選ばれやすくして焼きなましを行う。
+    // 返り値の二つ目は近傍ごとの試行回数と受理回数。observer には simulated_annealing と同じ event を送る。
+    let start_time = std::time::Instant::now();
+    statistics.set_acceptance_window_if_unset(number / ACCEPTANCE_RATE_POINT_NUMBER);
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let mut neighborhood_statistics = NEIGHBORHOODS.iter().map(|&neighborhood| NeighborhoodStatistics::new(neighborhood)).collect::<Vec<_>>();
+    state.init(&mut rng);
+    let mut best_score = state.get_score();
+    if neighborhood_statistics.is_empty() {
+        statistics.finish(start_time);
+        return (state, neighborhood_statistics);
+    }
+    let mut max_score = best_score;
+    for i in 0..number {
+        let temp = start_temp + (end_temp - start_temp) * (i as f64 / number as f64);
+        if observer.on_event(&SearchEvent::TemperatureStep { iteration: i, temperature: temp }) == SearchControl::Abort {
+            break;
+        }
+        let k = select_neighborhood(&neighborhood_statistics, &mut rng);
+        let mut now_state = state;
+        let now_score = match now_state.transition_with_neighborhood_and_delta(neighborhood_statistics[k].neighborhood, &mut rng) {
+            Some(score) => score,
+            None => now_state.get_score(),
+        };
+        statistics.generated_state_number += 1;
+        let probability = ((now_score - best_score) as f64 / temp).exp();
+        let is_accepted = now_score > best_score || probability > rng.next_f64();
+        neighborhood_statistics[k].record(is_accepted, now_score > best_score);
+        statistics.record_acceptance(is_accepted);
+        if is_accepted {
+            best_score = now_score;
+            std::mem::swap(&mut state, &mut now_state);
+        }
+        if best_score > max_score {
+            max_score = best_score;
+            if observer.on_event(&SearchEvent::NewBest { iteration: i, score: max_score as f64 }) == SearchControl::Abort {
+                break;
+            }
+        }
+        if observer.on_event(&SearchEvent::Iteration { iteration: i, score: best_score as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    (state, neighborhood_statistics)
+}
+
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Acceptance {
+    // iterated_local_search で、kick して局所探索した状態を次の出発点として受理する規則。
+    // スコアが改善したときだけ受理する。
+    Better,
+    // スコアが悪化しなければ受理する。
+    BetterOrEqual,
+    // 常に受理する。
+    Always,
+    // これまでの最良スコアからの悪化が指定した値以下なら受理する。
+    Threshold(isize),
+}
+#[allow(unused)]
+impl Acceptance {
+    fn is_accepted(&self, now_score: isize, candidate_score: isize, best_score: isize) -> bool {
+        match *self {
+            Acceptance::Better => candidate_score > now_score,
+            Acceptance::BetterOrEqual => candidate_score >= now_score,
+            Acceptance::Always => true,
+            Acceptance::Threshold(threshold) => candidate_score >= best_score - threshold,
+        }
+    }
+}
+impl std::str::FromStr for Acceptance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // コマンドラインで指定する名前。Threshold は `threshold:<悪化の許容量>` と書く。
+        match s {
+            "better" => Ok(Acceptance::Better),
+            "better-or-equal" => Ok(Acceptance::BetterOrEqual),
+            "always" => Ok(Acceptance::Always),
+            _ => match s.strip_prefix("threshold:") {
+                Some(threshold) => threshold.parse().map(Acceptance::Threshold).map_err(|_| format!("invalid threshold `{}`", threshold)),
+                None => Err(format!("unknown acceptance `{}`", s)),
+            },
+        }
+    }
+}
+#[allow(unused)]
+#[allow(clippy::too_many_arguments)]
+fn iterated_local_search_step(now: &mut (GameState, isize), best: &mut (GameState, isize), local_search_number: usize, kick_number: usize, acceptance: Acceptance, rng: &mut Xorshift, statistics: &mut SearchStatistics) -> bool {
+    // now を kick_number 回 kick してから局所探索し、acceptance に従って now を更新する。best が更新されたら true を返す。
+    // kick_number は kick の強さで、局所最適から離れる距離を決める。
+    let mut candidate_state = now.0;
+    for _ in 0..kick_number.max(1) {
+        candidate_state.kick(rng);
+    }
+    let candidate = local_search_with_observer(candidate_state, local_search_number, rng, statistics, &mut NoObserver);
+    let is_improved = candidate.1 > best.1;
+    if is_improved {
+        *best = candidate;
+    }
+    if acceptance.is_accepted(now.1, candidate.1, best.1) {
+        *now = candidate;
+    }
+    is_improved
+}
+#[allow(unused)]
+fn iterated_local_search(state: GameState, local_search_number: usize, kick_number: usize, iteration_number: usize, acceptance: Acceptance, seed: u64) -> GameState {
+    iterated_local_search_with_observer(state, local_search_number, kick_number, iteration_number, acceptance, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn iterated_local_search_with_observer(mut state: GameState, local_search_number: usize, kick_number: usize, iteration_number: usize, acceptance: Acceptance, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    // 受理率は局所探索の中の遷移について記録する。
+    // kick を一回行うたびに observer に Iteration を、最良スコアが更新されたら NewBest を送り、Abort が返されたらそこで打ち切る。
+    let start_time = std::time::Instant::now();
+    statistics.set_acceptance_window_if_unset((iteration_number + 1) * local_search_number / ACCEPTANCE_RATE_POINT_NUMBER);
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    state.init(&mut rng);
+    let mut now = local_search_with_observer(state, local_search_number, &mut rng, statistics, &mut NoObserver);
+    let mut best = now;
+    for iteration in 0..iteration_number {
+        let is_improved = iterated_local_search_step(&mut now, &mut best, local_search_number, kick_number, acceptance, &mut rng, statistics);
+        if is_improved && observer.on_event(&SearchEvent::NewBest { iteration, score: best.1 as f64 }) == SearchControl::Abort {
+            break;
+        }
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: now.1 as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    best.0
+}
+#[allow(unused)]
+fn iterated_local_search_with_time_threshold(state: GameState, local_search_number: usize, kick_number: usize, restart_number: usize, acceptance: Acceptance, time_threshold: f64, seed: u64) -> GameState {
+    iterated_local_search_with_time_threshold_and_observer(state, local_search_number, kick_number, restart_number, acceptance, time_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn iterated_local_search_with_time_threshold_and_observer(state: GameState, local_search_number: usize, kick_number: usize, restart_number: usize, acceptance: Acceptance, time_threshold: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    // time_threshold を restart_number 回の試行に等分し、それぞれ random な初期状態から反復局所探索を行う。
+    // 遷移の回数が前もって分からないので、受理率は呼び出し側が with_acceptance_window で区間を決めたときだけ記録する。
+    // observer には iterated_local_search と同じ event を、全ての試行を通した kick の番号で送る。NewBest は全体の最良スコアが更新されたときに送る。
+    let start_time = std::time::Instant::now();
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let restart_number = restart_number.max(1);
+    // best のスコアは -INF から始めるので、最初の試行の結果で必ず置き換わる。
+    let mut best = (state, -INF);
+    let mut iteration = 0;
+    let mut is_aborted = false;
+    for _ in 0..restart_number {
+        let time_keeper = TimeKeeper::build(time_threshold / restart_number as f64);
+        let mut now = local_search_with_observer(random(state, rng.gen::<u64>()), local_search_number, &mut rng, statistics, &mut NoObserver);
+        let mut restart_best = now;
+        while !is_aborted && !time_keeper.is_time_over() {
+            iterated_local_search_step(&mut now, &mut restart_best, local_search_number, kick_number, acceptance, &mut rng, statistics);
+            let is_improved = restart_best.1 > best.1;
+            if is_improved {
+                best = restart_best;
+            }
+            is_aborted = (is_improved && observer.on_event(&SearchEvent::NewBest { iteration, score: restart_best.1 as f64 }) == SearchControl::Abort)
+                || observer.on_event(&SearchEvent::Iteration { iteration, score: now.1 as f64 }) == SearchControl::Abort;
+            iteration += 1;
+        }
+        if restart_best.1 > best.1 {
+            best = restart_best;
+        }
+        if is_aborted {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    best.0
+}
+#[allow(unused)]
+struct Replica {
+    // parallel_tempering で用いる、一つの温度に対応する鎖。
+    state: GameState,
+    score: isize,
+    best_state: GameState,
+    best_score: isize,
+    rng: Xorshift,
+}
+#[allow(unused)]
+impl Replica {
+    fn new(mut state: GameState, seed: u64) -> Self {
+        let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+        state.init(&mut rng);
+        let score = state.get_score();
+        Self {
+            state,
+            score,
+            best_state: state,
+            best_score: score,
+            rng,
+        }
+    }
+
+    fn anneal(&mut self, number: usize, temp: f64) {
+        // 温度 temp を固定して number 回だけ遷移を試す。
+        for _ in 0..number {
+            let mut now_state = self.state;
+            let now_score = match now_state.transition_with_delta(&mut self.rng) {
+                Some(score) => score,
+                None => now_state.get_score(),
+            };
+            let probability = ((now_score - self.score) as f64 / temp).exp();
+            if now_score > self.score || probability > self.rng.next_f64() {
+                self.score = now_score;
+                self.state = now_state;
+                if self.score > self.best_score {
+                    self.best_score = self.score;
+                    self.best_state = self.state;
+                }
+            }
+        }
+    }
+}
+#[allow(unused)]
+fn swap_replicas(replicas: &mut [Replica], temps: &[f64], parity: usize, rng: &mut Xorshift, statistics: &mut SearchStatistics) {
+    // parity が 0 なら (0, 1), (2, 3), ...、1 なら (1, 2), (3, 4), ... の組で、隣り合う温度の鎖の状態の交換を試みる。
+    // 交換するのは状態とスコアだけで、各鎖の温度、最良状態、乱数はそのまま残す。
+    for k in (parity..temps.len().saturating_sub(1)).step_by(2) {
+        let delta = (replicas[k + 1].score - replicas[k].score) as f64 * (1.0 / temps[k] - 1.0 / temps[k + 1]);
+        let is_accepted = delta >= 0.0 || delta.exp() > rng.next_f64();
+        statistics.record_acceptance(is_accepted);
+        if is_accepted {
+            let (low, high) = replicas.split_at_mut(k + 1);
+            std::mem::swap(&mut low[k].state, &mut high[0].state);
+            std::mem::swap(&mut low[k].score, &mut high[0].score);
+        }
+    }
+}
+#[allow(unused)]
+fn parallel_tempering(state: GameState, number: usize, temps: &[f64], swap_interval: usize, is_multi_thread: bool, seed: u64) -> GameState {
+    parallel_tempering_with_observer(state, number, temps, swap_interval, is_multi_thread, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn parallel_tempering_with_observer(state: GameState, number: usize, temps: &[f64], swap_interval: usize, is_multi_thread: bool, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    // temps[k] の温度で鎖を一つずつ走らせ、swap_interval 回ごとに隣り合う温度の鎖で状態の交換を試みる。
+    // is_multi_thread が true のときは、交換と交換の間を鎖ごとに別スレッドで走らせる。
+    // 受理率は鎖の中の遷移ではなく、鎖の間の交換について記録する。
+    // 交換を試みるたびに observer に全ての鎖を通した最高スコアを Iteration として送り、それが更新されたら NewBest を送る。
+    // Abort が返されたらそこで打ち切る。
+    let start_time = std::time::Instant::now();
+    let swap_interval = swap_interval.max(1);
+    statistics.set_acceptance_window_if_unset(number / swap_interval * (temps.len() / 2) / ACCEPTANCE_RATE_POINT_NUMBER);
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let mut replicas = (0..temps.len()).map(|_| Replica::new(state, rng.gen::<u64>())).collect::<Vec<_>>();
+    let mut max_score = replicas.iter().map(|replica| replica.best_score).max().unwrap_or(-INF);
+    let mut i = 0;
+    while i < number {
+        let step = swap_interval.min(number - i);
+        if is_multi_thread {
+            std::thread::scope(|scope| {
+                for (replica, &temp) in replicas.iter_mut().zip(temps) {
+                    scope.spawn(move || replica.anneal(step, temp));
+                }
+            });
+        } else {
+            for (replica, &temp) in replicas.iter_mut().zip(temps) {
+                replica.anneal(step, temp);
+            }
+        }
+        statistics.generated_state_number += step * replicas.len();
+        swap_replicas(&mut replicas, temps, i / swap_interval % 2, &mut rng, statistics);
+        i += step;
+        let score = replicas.iter().map(|replica| replica.best_score).max().unwrap_or(-INF);
+        if score > max_score {
+            max_score = score;
+            if observer.on_event(&SearchEvent::NewBest { iteration: i, score: max_score as f64 }) == SearchControl::Abort {
+                break;
+            }
+        }
+        if observer.on_event(&SearchEvent::Iteration { iteration: i, score: max_score as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    match replicas.into_iter().max_by_key(|replica| replica.best_score) {
+        Some(replica) => replica.best_state,
+        None => random(state, rng.gen::<u64>()),
+    }
+}
+
+#[allow(unused)]
+fn tournament_selection(population: &[(isize, GameState)], tournament_size: usize, rng: &mut Xorshift) -> GameState {
+    // population から tournament_size 個を無作為に選び、その中で最もスコアの高いものを返す。
+    let mut best = population[rng.next_index(population.len())];
+    for _ in 1..tournament_size {
+        let candidate = population[rng.next_index(population.len())];
+        if candidate.0 > best.0 {
+            best = candidate;
+        }
+    }
+    best.1
+}
+#[allow(unused)]
+fn next_generation(population: &[(isize, GameState)], elite_number: usize, tournament_size: usize, mutation_rate: f64, rng: &mut Xorshift) -> Vec<(isize, GameState)> {
+    // スコアの高い順に並んだ population から、上位 elite_number 個をそのまま残し、残りを交叉と突然変異で作る。
+    let mut next_population = population.iter().take(elite_number).copied().collect::<Vec<_>>();
+    while next_population.len() < population.len() {
+        let first_parent = tournament_selection(population, tournament_size, rng);
+        let second_parent = tournament_selection(population, tournament_size, rng);
+        let mut child = first_parent.crossover(&second_parent, rng);
+        if rng.next_f64() < mutation_rate {
+            child.transition(rng);
+        }
+        next_population.push((child.get_score(), child));
+    }
+    next_population.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    next_population
+}
+#[allow(unused)]
+fn initial_population(state: GameState, population_size: usize, rng: &mut Xorshift) -> Vec<(isize, GameState)> {
+    let mut population = (0..population_size.max(1))
+        .map(|_| {
+            let mut now_state = random(state, rng.gen::<u64>());
+            (now_state.get_score(), now_state)
+        })
+        .collect::<Vec<_>>();
+    population.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    population
+}
+#[allow(unused)]
+fn notify_generation(population: &[(isize, GameState)], iteration: usize, max_score: &mut isize, observer: &mut dyn SearchObserver) -> SearchControl {
+    // スコアの高い順に並んだ population の先頭が max_score を超えたら NewBest を、続けて Iteration を observer に送る。
+    if population[0].0 > *max_score {
+        *max_score = population[0].0;
+        if observer.on_event(&SearchEvent::NewBest { iteration, score: *max_score as f64 }) == SearchControl::Abort {
+            return SearchControl::Abort;
+        }
+    }
+    observer.on_event(&SearchEvent::Iteration { iteration, score: population[0].0 as f64 })
+}
+#[allow(unused)]
+fn genetic_algorithm(state: GameState, population_size: usize, generation_number: usize, elite_number: usize, tournament_size: usize, mutation_rate: f64, seed: u64) -> GameState {
+    genetic_algorithm_with_observer(state, population_size, generation_number, elite_number, tournament_size, mutation_rate, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn genetic_algorithm_with_observer(state: GameState, population_size: usize, generation_number: usize, elite_number: usize, tournament_size: usize, mutation_rate: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    // 統計の層の数は作り終えた世代の数になる。
+    // 世代を一つ作るたびに observer に先頭の個体のスコアを Iteration として送り、最高スコアが更新されたら NewBest を送る。
+    // Abort が返されたらそこで打ち切る。
+    let start_time = std::time::Instant::now();
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let mut population = initial_population(state, population_size, &mut rng);
+    statistics.generated_state_number += population.len();
+    let mut max_score = population[0].0;
+    for iteration in 0..generation_number {
+        population = next_generation(&population, elite_number, tournament_size, mutation_rate, &mut rng);
+        statistics.generated_state_number += population.len().saturating_sub(elite_number);
+        statistics.completed_layer_number += 1;
+        if notify_generation(&population, iteration, &mut max_score, observer) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    population[0].1
+}
+#[allow(unused)]
+fn genetic_algorithm_with_time_threshold(state: GameState, population_size: usize, elite_number: usize, tournament_size: usize, mutation_rate: f64, time_threshold: f64, seed: u64) -> GameState {
+    genetic_algorithm_with_time_threshold_and_observer(state, population_size, elite_number, tournament_size, mutation_rate, time_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn genetic_algorithm_with_time_threshold_and_observer(state: GameState, population_size: usize, elite_number: usize, tournament_size: usize, mutation_rate: f64, time_threshold: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let mut population = initial_population(state, population_size, &mut rng);
+    statistics.generated_state_number += population.len();
+    let mut max_score = population[0].0;
+    for iteration in 0.. {
+        if time_keeper.is_time_over() {
+            break;
+        }
+        population = next_generation(&population, elite_number, tournament_size, mutation_rate, &mut rng);
+        statistics.generated_state_number += population.len().saturating_sub(elite_number);
+        statistics.completed_layer_number += 1;
+        if notify_generation(&population, iteration, &mut max_score, observer) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    population[0].1
+}
 
 #[allow(unused)]
 pub fn play_game(seed: u64) -> isize {
-    let _state = GameState::new(seed);
-    let mut state = random(_state);
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let _state = GameState::new(rng.gen::<u64>());
+    let mut state = random(_state, rng.gen::<u64>());
     state.get_score()
 }
 #[allow(unused)]
-pub fn test_ai_score(game_number: usize) -> f64 {
-    let mut rng = rand::thread_rng();
-    let mut score_sum = 0;
-    for _ in 0..game_number {
-        score_sum += play_game(rng.gen::<u64>());
-    }
-    score_sum as f64 / game_number as f64
+pub fn play_game_with_simulated_annealing(seed: u64, number: usize, start_temp: f64, end_temp: f64) -> isize {
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let state = GameState::new(rng.gen::<u64>());
+    let mut state = simulated_annealing(state, number, start_temp, end_temp, rng.gen::<u64>());
+    state.get_score()
+}
+#[allow(unused)]
+pub fn play_game_with_time_threshold(seed: u64, time_threshold: f64, local_search_number: usize, kick_number: usize, acceptance: Acceptance) -> isize {
+    // 盤面を作るのにかかった時間も time_threshold に含め、探索には残りの時間を渡す。
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let state = GameState::new(rng.gen::<u64>());
+    let search_time_threshold = time_threshold - start_time.elapsed().as_secs_f64();
+    let mut state = iterated_local_search_with_time_threshold(state, local_search_number, kick_number, 1, acceptance, search_time_threshold, rng.gen::<u64>());
+    state.get_score()
+}
+#[allow(unused)]
+pub fn test_ai_score(game_number: usize, seed: u64) -> ScoreSummary {
+    test_ai_score_with(&play_game, game_number, seed)
+}
+#[allow(unused)]
+pub fn test_ai_score_with(play_game: &dyn Fn(u64) -> isize, game_number: usize, seed: u64) -> ScoreSummary {
+    // play_game を game_seeds で seed から作ったシードの列で動かす。
+    ScoreSummary::build(game_seeds(game_number, seed).into_iter().map(|seed| (seed, play_game(seed))).collect())
+}
+#[allow(unused)]
+pub fn test_ai_score_in_parallel(game_number: usize, seed: u64, thread_number: usize, time_threshold: f64) -> EvaluationResult {
+    evaluate(&game_seeds(game_number, seed), thread_number, time_threshold, &|seed, time_threshold| play_game_with_time_threshold(seed, time_threshold, 5, 1, Acceptance::BetterOrEqual))
+}
+#[allow(unused)]
+pub fn compare_ai_score(play_game: &dyn Fn(u64) -> isize, other_play_game: &dyn Fn(u64) -> isize, game_number: usize, seed: u64) -> PairedComparison {
+    // 二つの play_game を同じシードの列で動かし、スコアの差を対応のある比較で評価する。
+    PairedComparison::build(game_seeds(game_number, seed).into_iter().map(|seed| (seed, play_game(seed), other_play_game(seed))).collect())
+}
+#[allow(unused)]
+pub fn tune_simulated_annealing(number: usize, method: TuningMethod, game_number: usize, seed: u64) -> Result<TuningResult, String> {
+    // number 回の遷移を行う simulated_annealing の start_temp と end_temp を、test_ai_score の平均スコアが高くなるように選ぶ。
+    let parameters = [
+        Parameter::build("start_temp", ParameterRange::Float { min: 1.0, max: 1000.0, is_log_scale: true })?,
+        Parameter::build("end_temp", ParameterRange::Float { min: 0.01, max: 10.0, is_log_scale: true })?,
+    ];
+    let objective = |configuration: &[f64], game_number: usize| {
+        let (start_temp, end_temp) = (configuration[0], configuration[1]);
+        test_ai_score_with(&|seed| play_game_with_simulated_annealing(seed, number, start_temp, end_temp), game_number, seed).mean
+    };
+    tune(&parameters, &objective, method, game_number)
 }
@@ -0,0 +1,417 @@
+use rand::Rng;
+
+// 正規分布の両側 95% 点。
+#[allow(unused)]
+const Z_95: f64 = 1.959_963_984_540_054;
+
+#[allow(unused)]
+pub fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+#[allow(unused)]
+pub fn standard_deviation(values: &[f64]) -> f64 {
+    // 不偏分散の平方根を返す。
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(values);
+    let variance = values.iter().map(|value| (value - mean) * (value - mean)).sum::<f64>() / (values.len() - 1) as f64;
+    variance.sqrt()
+}
+#[allow(unused)]
+pub fn confidence_interval_95(values: &[f64]) -> (f64, f64) {
+    // 平均の 95% 信頼区間を、自由度 n - 1 の t 分布で返す。数十局程度の少ない試行でも区間が狭くなりすぎない。
+    let mean = mean(values);
+    if values.len() < 2 {
+        return (mean, mean);
+    }
+    let half_width = t_95(values.len() - 1) * standard_deviation(values) / (values.len() as f64).sqrt();
+    (mean - half_width, mean + half_width)
+}
+fn ln_gamma(x: f64) -> f64 {
+    // x > 0 でのガンマ関数の対数を、Lanczos 近似 (g = 7) で返す。
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS.iter().enumerate().skip(1).fold(COEFFICIENTS[0], |series, (i, coefficient)| series + coefficient / (x + i as f64));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+fn incomplete_beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    // 正則化された不完全ベータ関数の連分数部分を、修正 Lentz 法で求める。
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    d = 1.0 / if d.abs() < TINY { TINY } else { d };
+    let mut fraction = d;
+    for m in 1..=300 {
+        let m = m as f64;
+        for numerator in [m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)), -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0))] {
+            d = 1.0 + numerator * d;
+            d = 1.0 / if d.abs() < TINY { TINY } else { d };
+            c = 1.0 + numerator / c;
+            c = if c.abs() < TINY { TINY } else { c };
+            fraction *= c * d;
+        }
+        if (c * d - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    fraction
+}
+fn regularized_incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    // I_x(a, b) を返す。連分数が速く収束する側で計算する。
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * incomplete_beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * incomplete_beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+#[allow(unused)]
+pub fn student_t_two_sided_p_value(t: f64, degrees_of_freedom: usize) -> f64 {
+    // 自由度 degrees_of_freedom の t 分布で、|T| >= |t| となる確率を返す。
+    if t.is_nan() || degrees_of_freedom == 0 {
+        return 1.0;
+    }
+    let nu = degrees_of_freedom as f64;
+    regularized_incomplete_beta(nu / 2.0, 0.5, nu / (nu + t * t))
+}
+#[allow(unused)]
+pub fn t_95(degrees_of_freedom: usize) -> f64 {
+    // 自由度 degrees_of_freedom の t 分布の両側 95% 点を、student_t_two_sided_p_value の二分探索で返す。
+    if degrees_of_freedom == 0 {
+        return f64::INFINITY;
+    }
+    let (mut low, mut high) = (Z_95, 2.0 * Z_95);
+    while student_t_two_sided_p_value(high, degrees_of_freedom) > 0.05 {
+        low = high;
+        high *= 2.0;
+    }
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+        if student_t_two_sided_p_value(middle, degrees_of_freedom) > 0.05 {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+#[allow(unused)]
+pub fn wilson_interval_95(points: &[f64]) -> (f64, f64) {
+    // 勝ちを 1、引き分けを 0.5、負けを 0 とした勝ち点の列から、勝率の 95% 信頼区間を Wilson のスコア区間で返す。
+    // 正規近似と違い、区間は [0, 1] からはみ出さず、全勝や全敗でも幅が 0 にならない。
+    if points.is_empty() {
+        return (0.0, 1.0);
+    }
+    let n = points.len() as f64;
+    let p = mean(points);
+    let z2 = Z_95 * Z_95;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half_width = Z_95 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / (1.0 + z2 / n);
+    ((center - half_width).max(0.0), (center + half_width).min(1.0))
+}
+#[allow(unused)]
+#[derive(Clone, Copy, Debug)]
+pub struct ArenaResult {
+    // arena の結果。すべて一つ目の AI から見た値。
+    pub win: usize,
+    pub draw: usize,
+    pub lose: usize,
+    // 勝ちを 1、引き分けを 0.5、負けを 0 とした勝ち点の平均と、その 95% 信頼区間 (Wilson のスコア区間)。
+    pub win_rate: f64,
+    pub win_rate_confidence_interval: (f64, f64),
+    // 終局時の評価値の平均と、その 95% 信頼区間。
+    pub average_score_margin: f64,
+    pub score_margin_confidence_interval: (f64, f64),
+}
+#[allow(unused)]
+impl ArenaResult {
+    pub fn build(points: &[f64], score_margins: &[f64]) -> Self {
+        // 一つ目の AI から見た各局の勝ち点と終局時の評価値から ArenaResult を作る。
+        Self {
+            win: points.iter().filter(|&&point| point == 1.0).count(),
+            draw: points.iter().filter(|&&point| point == 0.5).count(),
+            lose: points.iter().filter(|&&point| point == 0.0).count(),
+            win_rate: mean(points),
+            win_rate_confidence_interval: wilson_interval_95(points),
+            average_score_margin: mean(score_margins),
+            score_margin_confidence_interval: confidence_interval_95(score_margins),
+        }
+    }
+
+    pub fn play(game_number: usize, seed: u64, play_game: &dyn Fn(u64, bool) -> (f64, f64)) -> Self {
+        // seed から作った game_number 個のシードそれぞれで、一つ目の AI を先手にした局と後手にした局を一局ずつ play_game で対戦させる。
+        // play_game(game_seed, is_first) は、一つ目の AI が先手なら is_first を true として呼ばれ、先手から見た (勝ち点, 終局時の評価値) を返す。
+        let mut rngs: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+        let mut points = Vec::with_capacity(game_number * 2);
+        let mut score_margins = Vec::with_capacity(game_number * 2);
+        for _ in 0..game_number {
+            let game_seed = rngs.gen::<u64>();
+            let (point, score_margin) = play_game(game_seed, true);
+            points.push(point);
+            score_margins.push(score_margin);
+            let (point, score_margin) = play_game(game_seed, false);
+            points.push(1.0 - point);
+            score_margins.push(-score_margin);
+        }
+        Self::build(&points, &score_margins)
+    }
+}
+impl std::fmt::Display for ArenaResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "win {} / draw {} / lose {}, win rate {:.3} [{:.3}, {:.3}], score margin {:.3} [{:.3}, {:.3}]",
+            self.win,
+            self.draw,
+            self.lose,
+            self.win_rate,
+            self.win_rate_confidence_interval.0,
+            self.win_rate_confidence_interval.1,
+            self.average_score_margin,
+            self.score_margin_confidence_interval.0,
+            self.score_margin_confidence_interval.1
+        )
+    }
+}
+#[allow(unused)]
+pub fn bradley_terry_ratings(points: &[Vec<f64>], games: &[Vec<f64>]) -> Vec<f64> {
+    // points[i][j] は i が j から得た勝ち点 (引き分けは 0.5)、games[i][j] は i と j の対戦数を表す。
+    // Bradley-Terry モデルの強さを MM 法で推定し、平均が 1500 になる Elo の尺度で返す。
+    // 全勝や全敗でも発散しないよう、対戦した組ごとに一回の引き分けを加えて推定する。
+    let n = points.len();
+    let mut strengths = vec![1.0; n];
+    for _ in 0..1000 {
+        let mut next_strengths = vec![0.0; n];
+        for i in 0..n {
+            let mut win = 0.0;
+            let mut denominator = 0.0;
+            for j in (0..n).filter(|&j| j != i && games[i][j] > 0.0) {
+                win += points[i][j] + 0.5;
+                denominator += (games[i][j] + 1.0) / (strengths[i] + strengths[j]);
+            }
+            next_strengths[i] = if denominator > 0.0 { win / denominator } else { strengths[i] };
+        }
+        // 幾何平均が 1 になるよう正規化する。
+        let log_mean = next_strengths.iter().map(|strength: &f64| strength.ln()).sum::<f64>() / n as f64;
+        next_strengths.iter_mut().for_each(|strength| *strength /= log_mean.exp());
+        let difference = strengths.iter().zip(next_strengths.iter()).map(|(a, b): (&f64, &f64)| (a - b).abs()).fold(0.0, f64::max);
+        strengths = next_strengths;
+        if difference < 1e-12 {
+            break;
+        }
+    }
+    strengths.iter().map(|strength| 1500.0 + 400.0 * strength.log10()).collect()
+}
+#[allow(unused)]
+pub fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut values = values.to_vec();
+    values.sort_by(|a, b| a.total_cmp(b));
+    let n = values.len();
+    if n % 2 == 1 {
+        values[n / 2]
+    } else {
+        (values[n / 2 - 1] + values[n / 2]) / 2.0
+    }
+}
+
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct ScoreSummary {
+    // シードごとのスコアとその要約統計量。
+    pub scores: Vec<(u64, isize)>,
+    pub mean: f64,
+    pub standard_deviation: f64,
+    pub min: isize,
+    pub max: isize,
+    pub median: f64,
+    pub confidence_interval: (f64, f64),
+}
+#[allow(unused)]
+impl ScoreSummary {
+    pub fn build(scores: Vec<(u64, isize)>) -> Self {
+        // (シード, スコア) の列から要約統計量を計算する。
+        let values = scores.iter().map(|&(_, score)| score as f64).collect::<Vec<_>>();
+        Self {
+            mean: mean(&values),
+            standard_deviation: standard_deviation(&values),
+            min: scores.iter().map(|&(_, score)| score).min().unwrap_or(0),
+            max: scores.iter().map(|&(_, score)| score).max().unwrap_or(0),
+            median: median(&values),
+            confidence_interval: confidence_interval_95(&values),
+            scores,
+        }
+    }
+}
+impl std::fmt::Display for ScoreSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "games: {}, mean: {:.3} [{:.3}, {:.3}], sd: {:.3}, min: {}, median: {:.1}, max: {}",
+            self.scores.len(),
+            self.mean,
+            self.confidence_interval.0,
+            self.confidence_interval.1,
+            self.standard_deviation,
+            self.min,
+            self.median,
+            self.max
+        )
+    }
+}
+#[allow(unused)]
+fn sign_test_p_value(win: usize, lose: usize) -> f64 {
+    // 引き分けを除いた win + lose 回の中で勝ちと負けが等確率だという帰無仮説の、両側の正確な p 値を返す。
+    let n = win + lose;
+    let k = win.min(lose);
+    let ln_half_n = -(n as f64) * std::f64::consts::LN_2;
+    let mut ln_binomial = 0.0;
+    let mut tail = 0.0;
+    for i in 0..=k {
+        if i > 0 {
+            ln_binomial += ((n - i + 1) as f64).ln() - (i as f64).ln();
+        }
+        tail += (ln_binomial + ln_half_n).exp();
+    }
+    (2.0 * tail).min(1.0)
+}
+
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct PairedComparison {
+    // 同じシードで二つの AI を動かしたときの (シード, 一つ目のスコア, 二つ目のスコア)。
+    pub scores: Vec<(u64, isize, isize)>,
+    // 一つ目のスコアが二つ目より高かった、等しかった、低かったシードの数。
+    pub win: usize,
+    pub draw: usize,
+    pub lose: usize,
+    pub mean_difference: f64,
+    pub difference_confidence_interval: (f64, f64),
+    // 差の平均の対応のある t 統計量 (自由度はシード数 - 1) と、それによる両側 t 検定の p 値。
+    pub t_statistic: f64,
+    pub t_test_p_value: f64,
+    pub sign_test_p_value: f64,
+}
+#[allow(unused)]
+impl PairedComparison {
+    pub fn build(scores: Vec<(u64, isize, isize)>) -> Self {
+        let differences = scores.iter().map(|&(_, score, other_score)| (score - other_score) as f64).collect::<Vec<_>>();
+        let win = differences.iter().filter(|&&difference| difference > 0.0).count();
+        let lose = differences.iter().filter(|&&difference| difference < 0.0).count();
+        let mean_difference = mean(&differences);
+        let standard_error = standard_deviation(&differences) / (differences.len() as f64).sqrt();
+        let t_statistic = if standard_error > 0.0 {
+            mean_difference / standard_error
+        } else if mean_difference == 0.0 {
+            0.0
+        } else {
+            mean_difference.signum() * f64::INFINITY
+        };
+        Self {
+            win,
+            draw: differences.len() - win - lose,
+            lose,
+            mean_difference,
+            difference_confidence_interval: confidence_interval_95(&differences),
+            t_statistic,
+            t_test_p_value: student_t_two_sided_p_value(t_statistic, differences.len().saturating_sub(1)),
+            sign_test_p_value: sign_test_p_value(win, lose),
+            scores,
+        }
+    }
+}
+impl std::fmt::Display for PairedComparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "win: {}, draw: {}, lose: {}, mean difference: {:.3} [{:.3}, {:.3}], t: {:.3}, t test p: {:.4}, sign test p: {:.4}",
+            self.win,
+            self.draw,
+            self.lose,
+            self.mean_difference,
+            self.difference_confidence_interval.0,
+            self.difference_confidence_interval.1,
+            self.t_statistic,
+            self.t_test_p_value,
+            self.sign_test_p_value
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wilson_interval_stays_in_unit_range() {
+        for points in [vec![1.0; 2], vec![0.0; 2], vec![1.0, 0.5], vec![1.0; 100], vec![0.0, 0.5, 1.0, 1.0]] {
+            let (low, high) = wilson_interval_95(&points);
+            let p = mean(&points);
+            assert!(0.0 <= low && low <= p && p <= high && high <= 1.0, "{:?} -> ({}, {})", points, low, high);
+            assert!(high - low > 0.0);
+        }
+    }
+
+    #[test]
+    fn median_of_odd_and_even_lengths() {
+        assert_eq!(median(&[3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&[4.0, 1.0, 3.0, 2.0]), 2.5);
+        assert_eq!(median(&[]), 0.0);
+    }
+
+    #[test]
+    fn standard_deviation_of_one_value_is_zero() {
+        assert_eq!(standard_deviation(&[5.0]), 0.0);
+        assert_eq!(confidence_interval_95(&[5.0]), (5.0, 5.0));
+        assert!((standard_deviation(&[1.0, 2.0, 3.0, 4.0]) - (5.0f64 / 3.0).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn t_95_matches_the_table() {
+        for (degrees_of_freedom, t) in [(1, 12.706_205), (2, 4.302_653), (4, 2.776_445), (9, 2.262_157), (29, 2.045_230), (1000, 1.962_339)] {
+            assert!((t_95(degrees_of_freedom) - t).abs() < 1e-5, "{}: {}", degrees_of_freedom, t_95(degrees_of_freedom));
+        }
+        // 平均 3、標準偏差 sqrt(2.5) の五つの値の区間は、正規近似の 1.96 倍ではなく 2.776 倍に広がる。
+        let (low, high) = confidence_interval_95(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let half_width = 2.776_445 * (2.5f64 / 5.0).sqrt();
+        assert!((low - (3.0 - half_width)).abs() < 1e-5 && (high - (3.0 + half_width)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn t_test_p_value_matches_known_values() {
+        // 自由度 1 の t 分布は Cauchy 分布なので、p 値は 1 - 2 atan(|t|) / π になる。
+        for t in [0.0, 0.5, 1.0, 3.0, -7.0] {
+            assert!((student_t_two_sided_p_value(t, 1) - (1.0 - 2.0 * f64::atan(f64::abs(t)) / std::f64::consts::PI)).abs() < 1e-10);
+        }
+        // 差が 1, 2, 3, 4, 5 のとき、t = 3 / sqrt(0.5)、自由度 4 で、両側 p 値は 0.013236 になる。
+        let comparison = PairedComparison::build((1..=5).map(|difference| (difference as u64, difference as isize, 0)).collect());
+        assert!((comparison.t_statistic - 3.0 / 0.5f64.sqrt()).abs() < 1e-12);
+        assert!((comparison.t_test_p_value - 0.013_236).abs() < 1e-6, "{}", comparison.t_test_p_value);
+        assert_eq!(comparison.sign_test_p_value, 0.0625);
+        let comparison = PairedComparison::build(vec![(0, 1, 1), (1, 2, 2)]);
+        assert_eq!(comparison.t_test_p_value, 1.0);
+    }
+}
@@ -1,19 +1,88 @@
+#[allow(unused)]
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    // 別のスレッドから探索を止めるための旗。clone したものは同じ旗を共有する。
+    is_cancelled: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+#[allow(unused)]
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.is_cancelled.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.is_cancelled.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    pub fn run<T>(&self, f: impl FnOnce() -> T) -> T {
+        // f の中でこのスレッドが TimeKeeper::build で作る TimeKeeper はすべてこの旗を見て、cancel されたら時間切れとして扱う。
+        // そのため、時間で打ち切る探索はどれも、cancel されるとそこまでの最善の結果を返す。
+        // 旗はスレッドごとに持つので、f の中で新しく立てたスレッドには伝わらない。そのスレッドでは current で受け取った旗の run を呼ぶか、
+        // TimeKeeper::with_cancellation を使うこと。evaluate はそうしている。f が panic しても、元の旗は戻す。
+        let _guard = CurrentCancellationTokenGuard {
+            previous: CURRENT_CANCELLATION_TOKEN.with(|token| token.replace(Some(self.clone()))),
+        };
+        f()
+    }
+
+    pub fn current() -> Option<Self> {
+        // このスレッドで実行中の run の旗を返す。run の外なら None を返す。
+        CURRENT_CANCELLATION_TOKEN.with(|token| token.borrow().clone())
+    }
+}
+
+thread_local! {
+    // CancellationToken::run の中で作られた TimeKeeper が受け取る旗。
+    static CURRENT_CANCELLATION_TOKEN: std::cell::RefCell<Option<CancellationToken>> = const { std::cell::RefCell::new(None) };
+}
+
+struct CurrentCancellationTokenGuard {
+    // run を抜けるときに、panic で抜けた場合も含めて元の旗に戻す。
+    previous: Option<CancellationToken>,
+}
+impl Drop for CurrentCancellationTokenGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT_CANCELLATION_TOKEN.with(|token| token.replace(previous));
+    }
+}
+
 #[allow(unused)]
 pub struct TimeKeeper {
     start_time: std::time::Instant,
     time_threshold: f64,
+    cancellation_token: Option<CancellationToken>,
 }
 #[allow(unused)]
 impl TimeKeeper {
     pub fn build(time_threshold: f64) -> Self {
+        // CancellationToken::run の中で作られたときは、その旗も見る。
+        Self {
+            start_time: std::time::Instant::now(),
+            time_threshold,
+            cancellation_token: CancellationToken::current(),
+        }
+    }
+
+    pub fn with_cancellation(time_threshold: f64, cancellation_token: &CancellationToken) -> Self {
+        // run を通さずに、cancellation_token を明示して作る。どのスレッドで作っても旗が効く。
         Self {
             start_time: std::time::Instant::now(),
             time_threshold,
+            cancellation_token: Some(cancellation_token.clone()),
         }
     }
 
     #[inline]
     pub fn is_time_over(&self) -> bool {
+        if self.cancellation_token.as_ref().is_some_and(|token| token.is_cancelled()) {
+            return true;
+        }
         let elapsed_time = self.start_time.elapsed().as_nanos() as f64 * 1e-9;
         #[cfg(feature = "local")]
         {
@@ -24,4 +93,36 @@ impl TimeKeeper {
             elapsed_time >= self.time_threshold
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previous_token_is_restored_after_panic() {
+        let outer = CancellationToken::new();
+        let inner = CancellationToken::new();
+        outer.run(|| {
+            let result = std::panic::catch_unwind(|| inner.run(|| panic!("search failed")));
+            assert!(result.is_err());
+            inner.cancel();
+            assert!(!TimeKeeper::build(f64::INFINITY).is_time_over());
+            outer.cancel();
+            assert!(TimeKeeper::build(f64::INFINITY).is_time_over());
+        });
+        assert!(CancellationToken::current().is_none());
+    }
+
+    #[test]
+    fn with_cancellation_works_on_other_threads() {
+        let token = CancellationToken::new();
+        let time_keeper = TimeKeeper::with_cancellation(f64::INFINITY, &token);
+        assert!(!time_keeper.is_time_over());
+        std::thread::scope(|scope| {
+            scope.spawn(|| token.cancel());
+        });
+        assert!(time_keeper.is_time_over());
+        assert!(std::thread::scope(|scope| scope.spawn(|| TimeKeeper::with_cancellation(f64::INFINITY, &token).is_time_over()).join().unwrap()));
+    }
+}
@@ -0,0 +1,100 @@
+#[allow(unused)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReplacementPolicy {
+    // 同じ位置に別の局面が入っていたら、常に新しい値で上書きする。
+    Always,
+    // 同じ位置に別の局面が入っていたら、新しい値の深さが既存の値の深さ以上のときだけ上書きする。
+    DepthPreferred,
+}
+
+#[allow(unused)]
+#[derive(Clone, Copy)]
+struct Entry<T> {
+    hash: u64,
+    depth: usize,
+    value: T,
+}
+
+#[allow(unused)]
+pub struct TranspositionTable<T: Copy> {
+    // hash の下位ビットで位置を決める、固定長の置換表。
+    entries: Vec<Option<Entry<T>>>,
+    replacement_policy: ReplacementPolicy,
+}
+#[allow(unused)]
+impl<T: Copy> TranspositionTable<T> {
+    pub fn build(size: usize, replacement_policy: ReplacementPolicy) -> Self {
+        // size 以上の最小の 2 冪の大きさの置換表を作る。
+        Self {
+            entries: vec![None; size.max(1).next_power_of_two()],
+            replacement_policy,
+        }
+    }
+
+    #[inline]
+    fn index(&self, hash: u64) -> usize {
+        hash as usize & (self.entries.len() - 1)
+    }
+
+    pub fn get(&self, hash: u64) -> Option<(usize, T)> {
+        // hash の局面が登録されていれば、(登録したときの深さ, 値) を返す。
+        match self.entries[self.index(hash)] {
+            Some(entry) if entry.hash == hash => Some((entry.depth, entry.value)),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, hash: u64, depth: usize, value: T) {
+        // 同じ局面の値は常に上書きし、別の局面の値は replacement_policy に従って上書きする。
+        let index = self.index(hash);
+        let is_replaced = match self.entries[index] {
+            None => true,
+            Some(entry) if entry.hash == hash => true,
+            Some(entry) => match self.replacement_policy {
+                ReplacementPolicy::Always => true,
+                ReplacementPolicy::DepthPreferred => depth >= entry.depth,
+            },
+        };
+        if is_replaced {
+            self.entries[index] = Some(Entry {
+                hash,
+                depth,
+                value,
+            });
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_follows_replacement_policy() {
+        // 大きさ 4 の置換表では、hash 1 と hash 5 が同じ位置に入る。
+        for replacement_policy in [ReplacementPolicy::Always, ReplacementPolicy::DepthPreferred] {
+            let mut table = TranspositionTable::build(4, replacement_policy);
+            table.insert(1, 5, 'a');
+            table.insert(1, 1, 'b');
+            assert_eq!(table.get(1), Some((1, 'b')));
+            table.insert(5, 0, 'c');
+            match replacement_policy {
+                ReplacementPolicy::Always => {
+                    assert_eq!(table.get(1), None);
+                    assert_eq!(table.get(5), Some((0, 'c')));
+                }
+                ReplacementPolicy::DepthPreferred => {
+                    assert_eq!(table.get(1), Some((1, 'b')));
+                    assert_eq!(table.get(5), None);
+                    table.insert(5, 1, 'd');
+                    assert_eq!(table.get(1), None);
+                    assert_eq!(table.get(5), Some((1, 'd')));
+                }
+            }
+        }
+    }
+}
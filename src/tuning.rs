@@ -0,0 +1,242 @@
+use crate::random::Xorshift;
+
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub enum ParameterRange {
+    // 候補の値を列挙する。
+    Values(Vec<f64>),
+    // min 以上 max 以下の整数。
+    Int(isize, isize),
+    // min 以上 max 以下の実数。is_log_scale なら対数をとった値の上で一様に扱う。
+    Float { min: f64, max: f64, is_log_scale: bool },
+}
+#[allow(unused)]
+impl ParameterRange {
+    pub fn validate(&self) -> Result<(), String> {
+        // 値を一つも選べない範囲や、対数をとれない範囲を弾く。
+        match *self {
+            ParameterRange::Values(ref values) => {
+                if values.is_empty() {
+                    return Err("no candidate values".to_string());
+                }
+                if let Some(value) = values.iter().find(|value| !value.is_finite()) {
+                    return Err(format!("candidate value {} is not finite", value));
+                }
+            }
+            ParameterRange::Int(min, max) => {
+                if min > max {
+                    return Err(format!("min {} is greater than max {}", min, max));
+                }
+            }
+            ParameterRange::Float { min, max, is_log_scale } => {
+                if !min.is_finite() || !max.is_finite() {
+                    return Err(format!("range [{}, {}] is not finite", min, max));
+                }
+                if min > max {
+                    return Err(format!("min {} is greater than max {}", min, max));
+                }
+                if is_log_scale && min <= 0.0 {
+                    return Err(format!("log scale needs a positive min but min is {}", min));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct Parameter {
+    pub name: String,
+    pub range: ParameterRange,
+}
+#[allow(unused)]
+impl Parameter {
+    pub fn build(name: &str, range: ParameterRange) -> Result<Self, String> {
+        range.validate().map_err(|error| format!("parameter `{}`: {}", name, error))?;
+        Ok(Self {
+            name: name.to_string(),
+            range,
+        })
+    }
+
+    fn grid(&self, grid_size: usize) -> Vec<f64> {
+        // grid search で試す値を返す。実数は grid_size 個の等間隔の点で代表させる。
+        match self.range {
+            ParameterRange::Values(ref values) => values.clone(),
+            ParameterRange::Int(min, max) => (min..=max).map(|value| value as f64).collect(),
+            ParameterRange::Float { min, max, is_log_scale } => {
+                let grid_size = grid_size.max(1);
+                (0..grid_size)
+                    .map(|k| {
+                        let t = if grid_size == 1 { 0.5 } else { k as f64 / (grid_size - 1) as f64 };
+                        if is_log_scale {
+                            (min.ln() + (max.ln() - min.ln()) * t).exp()
+                        } else {
+                            min + (max - min) * t
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn sample(&self, rng: &mut Xorshift) -> f64 {
+        match self.range {
+            ParameterRange::Values(ref values) => values[rng.next_index(values.len())],
+            ParameterRange::Int(min, max) => (min + rng.next_index((max - min + 1) as usize) as isize) as f64,
+            ParameterRange::Float { min, max, is_log_scale } => {
+                if is_log_scale {
+                    (min.ln() + (max.ln() - min.ln()) * rng.next_f64()).exp()
+                } else {
+                    min + (max - min) * rng.next_f64()
+                }
+            }
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Clone, Copy, Debug)]
+pub enum TuningMethod {
+    // すべての組み合わせを試す。実数の引数は grid_size 個の点で試す。
+    Grid { grid_size: usize },
+    // trial_number 個の組み合わせを無作為に選んで試す。
+    Random { trial_number: usize, seed: u64 },
+    // trial_number 個の組み合わせを無作為に選び、評価するゲーム数を eta 倍にしながら上位 1 / eta だけを残していく。
+    SuccessiveHalving { trial_number: usize, eta: usize, seed: u64 },
+}
+
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct Trial {
+    pub configuration: Vec<f64>,
+    pub game_number: usize,
+    pub score: f64,
+}
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct TuningResult {
+    pub parameter_names: Vec<String>,
+    // 評価したゲーム数が多い順、同じならスコアが高い順に並んだ試行。先頭が最良の組み合わせ。
+    pub trials: Vec<Trial>,
+}
+#[allow(unused)]
+impl TuningResult {
+    pub fn best(&self) -> Option<&Trial> {
+        self.trials.first()
+    }
+}
+impl std::fmt::Display for TuningResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let widths = self.parameter_names.iter().map(|name| name.len().max(10)).collect::<Vec<_>>();
+        write!(f, "{:>4}", "rank")?;
+        for (name, &width) in self.parameter_names.iter().zip(widths.iter()) {
+            write!(f, "  {:>width$}", name)?;
+        }
+        writeln!(f, "  {:>5}  {:>10}", "games", "score")?;
+        for (rank, trial) in self.trials.iter().enumerate() {
+            write!(f, "{:>4}", rank + 1)?;
+            for (value, &width) in trial.configuration.iter().zip(widths.iter()) {
+                write!(f, "  {:>width$.4}", value)?;
+            }
+            writeln!(f, "  {:>5}  {:>10.3}", trial.game_number, trial.score)?;
+        }
+        Ok(())
+    }
+}
+
+#[allow(unused)]
+fn evaluate_trials(configurations: Vec<Vec<f64>>, objective: &dyn Fn(&[f64], usize) -> f64, game_number: usize) -> Vec<Trial> {
+    configurations
+        .into_iter()
+        .map(|configuration| Trial {
+            score: objective(&configuration, game_number),
+            configuration,
+            game_number,
+        })
+        .collect()
+}
+#[allow(unused)]
+pub fn tune(parameters: &[Parameter], objective: &dyn Fn(&[f64], usize) -> f64, method: TuningMethod, game_number: usize) -> Result<TuningResult, String> {
+    // objective(configuration, game_number) は、parameters の順に並んだ値で game_number ゲームを行ったときのスコアを返す。
+    // スコアが高いほど良いとする。同じ game_number なら同じシードの列で評価すると、組み合わせの比較が公平になる。
+    // SuccessiveHalving では game_number が最初の段階で評価するゲーム数になる。
+    // 値を選べない範囲の引数があれば、評価を始める前にエラーを返す。
+    for parameter in parameters.iter() {
+        parameter.range.validate().map_err(|error| format!("parameter `{}`: {}", parameter.name, error))?;
+    }
+    let mut trials = match method {
+        TuningMethod::Grid { grid_size } => {
+            let mut configurations = vec![Vec::new()];
+            for parameter in parameters.iter() {
+                let values = parameter.grid(grid_size);
+                configurations = configurations
+                    .iter()
+                    .flat_map(|configuration| {
+                        values.iter().map(move |&value| {
+                            let mut configuration = configuration.clone();
+                            configuration.push(value);
+                            configuration
+                        })
+                    })
+                    .collect();
+            }
+            evaluate_trials(configurations, objective, game_number)
+        }
+        TuningMethod::Random { trial_number, seed } => {
+            let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+            let configurations = (0..trial_number).map(|_| parameters.iter().map(|parameter| parameter.sample(&mut rng)).collect()).collect();
+            evaluate_trials(configurations, objective, game_number)
+        }
+        TuningMethod::SuccessiveHalving { trial_number, eta, seed } => {
+            let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+            let eta = eta.max(2);
+            let mut configurations = (0..trial_number).map(|_| parameters.iter().map(|parameter| parameter.sample(&mut rng)).collect()).collect::<Vec<Vec<f64>>>();
+            let mut game_number = game_number.max(1);
+            let mut trials = Vec::new();
+            while !configurations.is_empty() {
+                let mut rung = evaluate_trials(configurations, objective, game_number);
+                rung.sort_by(|a, b| b.score.total_cmp(&a.score));
+                configurations = if rung.len() == 1 {
+                    Vec::new()
+                } else {
+                    rung.iter().take(rung.len().div_ceil(eta)).map(|trial| trial.configuration.clone()).collect()
+                };
+                trials.extend(rung);
+                game_number *= eta;
+            }
+            trials
+        }
+    };
+    trials.sort_by(|a, b| b.game_number.cmp(&a.game_number).then(b.score.total_cmp(&a.score)));
+    Ok(TuningResult {
+        parameter_names: parameters.iter().map(|parameter| parameter.name.clone()).collect(),
+        trials,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_or_reversed_ranges_are_rejected() {
+        assert!(Parameter::build("x", ParameterRange::Values(vec![])).is_err());
+        assert!(Parameter::build("x", ParameterRange::Int(3, 2)).is_err());
+        assert!(Parameter::build("x", ParameterRange::Float { min: 1.0, max: 0.5, is_log_scale: false }).is_err());
+        assert!(Parameter::build("x", ParameterRange::Float { min: 0.0, max: 1.0, is_log_scale: true }).is_err());
+        assert!(Parameter::build("x", ParameterRange::Int(2, 2)).is_ok());
+        let parameters = [Parameter {
+            name: "x".to_string(),
+            range: ParameterRange::Values(vec![]),
+        }];
+        assert!(tune(&parameters, &|_, _| 0.0, TuningMethod::Random { trial_number: 3, seed: 0 }, 1).is_err());
+    }
+
+    #[test]
+    fn grid_search_finds_the_best_configuration() {
+        let parameters = [Parameter::build("x", ParameterRange::Int(-3, 3)).unwrap()];
+        let result = tune(&parameters, &|configuration, _| -(configuration[0] - 1.0).abs(), TuningMethod::Grid { grid_size: 5 }, 1).unwrap();
+        assert_eq!(result.best().unwrap().configuration, vec![1.0]);
+    }
+}
@@ -0,0 +1,329 @@
+use rand::*;
+
+use crate::search_observer::{NoObserver, SearchControl, SearchEvent, SearchObserver};
+use crate::search_statistics::SearchStatistics;
+use crate::statistics::ArenaResult;
+use crate::time_keeper::TimeKeeper;
+use crate::two_player_alternate_game::{alpha_beta_score_with_statistics, playout, Action, GameState as DeterminizedGameState, INF};
+
+#[derive(Clone, Copy)]
+#[allow(unused)]
+pub struct GameState {
+    // 実際の状態。player はこれを直接見ることはできない。
+    state: DeterminizedGameState,
+    // player が観測できる情報を表す。
+}
+#[allow(unused)]
+impl GameState {
+    pub fn new(seed: u64) -> Self {
+        // GameState をシード値を用いて作成する。
+        Self {
+            state: DeterminizedGameState::new(seed),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.state.is_done()
+    }
+
+    fn advance(&mut self, action: Action) {
+        // 実際の状態を一手進め、新たに観測できた情報を更新する関数。
+        self.state.advance(action);
+    }
+
+    fn legal_actions(&self) -> Vec<Action> {
+        // 手番の player に許容される action の全体を返す関数。観測できる情報だけから決まる必要がある。
+        self.state.legal_actions()
+    }
+
+    fn determinize(&self, seed: u64) -> DeterminizedGameState {
+        let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+        // 観測できない情報を rng でサンプリングし、観測と矛盾しない完全情報の状態を作る関数。
+        // 簡易実装では、実際の状態をそのまま返す。
+        self.state
+    }
+
+    fn is_first_player(&self) -> bool {
+        self.state.is_first_player()
+    }
+
+    fn get_first_player_score_for_win_rate(&self) -> f64 {
+        self.state.get_first_player_score_for_win_rate()
+    }
+
+    fn get_first_player_score(&self) -> isize {
+        // 先手から見た実際の盤面の評価値を返す。
+        self.state.get_first_player_score()
+    }
+}
+
+// ここから先は、action を決定する方法を定める関数を作る。
+#[allow(unused)]
+pub fn random_action(state: &GameState, rng: &mut rand::rngs::StdRng) -> Option<Action> {
+    let legal_actions = state.legal_actions();
+    if legal_actions.is_empty() {
+        return None;
+    }
+    Some(legal_actions[rng.gen::<usize>() % legal_actions.len()])
+}
+#[allow(unused)]
+fn pimc_scores(state: &GameState, legal_actions: &[Action], depth: usize, score_sums: &mut [isize], rng: &mut rand::rngs::StdRng, statistics: &mut SearchStatistics) {
+    // determinization を一つ作り、その上で各 action を alpha_beta 法で評価した値を score_sums に足す。
+    let determinized_state = state.determinize(rng.gen::<u64>());
+    statistics.expanded_node_number += 1;
+    for (score_sum, &action) in score_sums.iter_mut().zip(legal_actions.iter()) {
+        let mut next_state = determinized_state;
+        next_state.advance(action);
+        statistics.generated_state_number += 1;
+        *score_sum -= alpha_beta_score_with_statistics(&next_state, -INF, INF, depth.saturating_sub(1), statistics);
+    }
+    statistics.record_depth(depth);
+}
+#[allow(unused)]
+fn best_score_action(legal_actions: &[Action], score_sums: &[isize]) -> Option<Action> {
+    let mut best_action = None;
+    let mut best_score = -INF;
+    for (&action, &score) in legal_actions.iter().zip(score_sums.iter()) {
+        if best_action.is_none() || score > best_score {
+            best_action = Some(action);
+            best_score = score;
+        }
+    }
+    best_action
+}
+#[allow(unused)]
+fn best_average_score(score_sums: &[isize], determinization_number: usize) -> f64 {
+    match score_sums.iter().max() {
+        Some(&score_sum) => score_sum as f64 / determinization_number as f64,
+        None => -INF as f64,
+    }
+}
+#[allow(unused)]
+pub fn pimc_action(state: &GameState, determinization_number: usize, depth: usize, seed: u64) -> Option<Action> {
+    pimc_action_with_observer(state, determinization_number, depth, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn pimc_action_with_observer(state: &GameState, determinization_number: usize, depth: usize, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // Perfect Information Monte Carlo。determinization_number 個の determinization それぞれで
+    // 各 action を depth 手先まで読み、評価値の和が最大の action を返す。
+    // determinization を一つ調べるたびに observer に最善の action の評価値の平均を Iteration として送り、Abort が返されたらそこまでの和で action を選ぶ。
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let legal_actions = state.legal_actions();
+    let mut score_sums = vec![0; legal_actions.len()];
+    for iteration in 0..determinization_number {
+        pimc_scores(state, &legal_actions, depth, &mut score_sums, &mut rng, statistics);
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: best_average_score(&score_sums, iteration + 1) }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    best_score_action(&legal_actions, &score_sums)
+}
+#[allow(unused)]
+pub fn pimc_action_with_time_threshold(state: &GameState, depth: usize, time_threshold: f64, seed: u64) -> Option<Action> {
+    pimc_action_with_time_threshold_and_observer(state, depth, time_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn pimc_action_with_time_threshold_and_observer(state: &GameState, depth: usize, time_threshold: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let legal_actions = state.legal_actions();
+    let mut score_sums = vec![0; legal_actions.len()];
+    for iteration in 0.. {
+        if time_keeper.is_time_over() {
+            break;
+        }
+        pimc_scores(state, &legal_actions, depth, &mut score_sums, &mut rng, statistics);
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: best_average_score(&score_sums, iteration + 1) }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    best_score_action(&legal_actions, &score_sums)
+}
+#[allow(unused)]
+struct Node {
+    // Information Set MCTS の節点。盤面は持たず、根からの action の列で情報集合を表す。
+    // w は手番の player から見た勝ち点の和、n は訪問回数、availability はこの action が合法だった回数を表す。
+    w: f64,
+    n: usize,
+    availability: usize,
+    child_nodes: Vec<(Action, Node)>,
+}
+#[allow(unused)]
+impl Node {
+    fn new() -> Self {
+        Self {
+            w: 0.0,
+            n: 0,
+            availability: 0,
+            child_nodes: Vec::new(),
+        }
+    }
+
+    fn evaluate(&mut self, state: &mut DeterminizedGameState, c: f64, expand_threshold: usize, depth: usize, rng: &mut rand::rngs::StdRng, statistics: &mut SearchStatistics) -> f64 {
+        // determinization された state の上で一回だけ探索し、手番の player から見た勝ち点を返す。
+        // state の合法手のうちまだ子を持たないものは、訪問回数が expand_threshold に達してから子として加える。
+        statistics.record_depth(depth);
+        let value = if state.is_done() {
+            playout(state, rng)
+        } else {
+            let legal_actions = state.legal_actions();
+            if self.n + 1 >= expand_threshold {
+                let child_node_number = self.child_nodes.len();
+                for action in legal_actions.iter() {
+                    if self.child_nodes.iter().all(|(child_action, _)| child_action != action) {
+                        self.child_nodes.push((*action, Node::new()));
+                    }
+                }
+                if self.child_nodes.len() > child_node_number {
+                    statistics.expanded_node_number += 1;
+                    statistics.generated_state_number += self.child_nodes.len() - child_node_number;
+                }
+            }
+            match self.next_child_node(&legal_actions, c) {
+                Some((action, child_node)) => {
+                    state.advance(action);
+                    1.0 - child_node.evaluate(state, c, expand_threshold, depth + 1, rng, statistics)
+                }
+                None => playout(state, rng),
+            }
+        };
+        self.w += value;
+        self.n += 1;
+        value
+    }
+
+    fn next_child_node(&mut self, legal_actions: &[Action], c: f64) -> Option<(Action, &mut Node)> {
+        // state で合法な子のうち、訪問していないものがあればそれを、なければ UCB1 が最大のものを返す。
+        let mut best_k = None;
+        let mut best_ucb1 = 0.0;
+        for (k, (action, child_node)) in self.child_nodes.iter_mut().enumerate() {
+            if !legal_actions.contains(action) {
+                continue;
+            }
+            child_node.availability += 1;
+            let ucb1 = if child_node.n == 0 {
+                f64::INFINITY
+            } else {
+                1.0 - child_node.w / child_node.n as f64 + c * (2.0 * (child_node.availability as f64).ln() / child_node.n as f64).sqrt()
+            };
+            if best_k.is_none() || ucb1 > best_ucb1 {
+                best_k = Some(k);
+                best_ucb1 = ucb1;
+            }
+        }
+        let k = best_k?;
+        let (action, child_node) = &mut self.child_nodes[k];
+        Some((*action, child_node))
+    }
+
+    fn most_visited_action(&self) -> Option<Action> {
+        let mut best_action = None;
+        let mut best_n = 0;
+        for (action, child_node) in self.child_nodes.iter() {
+            if best_action.is_none() || child_node.n > best_n {
+                best_action = Some(*action);
+                best_n = child_node.n;
+            }
+        }
+        best_action
+    }
+}
+#[allow(unused)]
+pub fn ismcts_action(state: &GameState, playout_number: usize, c: f64, expand_threshold: usize, seed: u64) -> Option<Action> {
+    ismcts_action_with_observer(state, playout_number, c, expand_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn ismcts_action_with_observer(state: &GameState, playout_number: usize, c: f64, expand_threshold: usize, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // Information Set MCTS。探索のたびに determinization を作り直し、一つの木で情報集合ごとの統計をとる。
+    // 一回探索するたびに observer に根の平均の勝ち点を Iteration として送り、Abort が返されたらそこまでの統計で action を選ぶ。
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut root_node = Node::new();
+    for iteration in 0..playout_number {
+        let mut determinized_state = state.determinize(rng.gen::<u64>());
+        root_node.evaluate(&mut determinized_state, c, expand_threshold, 0, &mut rng, statistics);
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: root_node.w / root_node.n as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    root_node.most_visited_action()
+}
+#[allow(unused)]
+pub fn ismcts_action_with_time_threshold(state: &GameState, c: f64, expand_threshold: usize, time_threshold: f64, seed: u64) -> Option<Action> {
+    ismcts_action_with_time_threshold_and_observer(state, c, expand_threshold, time_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn ismcts_action_with_time_threshold_and_observer(state: &GameState, c: f64, expand_threshold: usize, time_threshold: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut root_node = Node::new();
+    for iteration in 0.. {
+        if time_keeper.is_time_over() {
+            break;
+        }
+        let mut determinized_state = state.determinize(rng.gen::<u64>());
+        root_node.evaluate(&mut determinized_state, c, expand_threshold, 0, &mut rng, statistics);
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: root_node.w / root_node.n as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    root_node.most_visited_action()
+}
+
+#[allow(unused)]
+pub fn play_game(seed: u64) -> f64 {
+    // 先手が ismcts_action、後手が pimc_action で対戦し、先手から見た勝ち点を返す。
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut state = GameState::new(rng.gen::<u64>());
+    while !state.is_done() {
+        let action = if state.is_first_player() {
+            ismcts_action(&state, 1000, 1.0, 10, rng.gen::<u64>())
+        } else {
+            pimc_action(&state, 20, 4, rng.gen::<u64>())
+        };
+        match action {
+            Some(action) => state.advance(action),
+            None => break,
+        }
+    }
+    state.get_first_player_score_for_win_rate()
+}
+#[allow(unused)]
+pub fn test_first_player_win_rate(game_number: usize, seed: u64) -> f64 {
+    let mut rngs: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut score_sum = 0.0;
+    for _ in 0..game_number {
+        score_sum += play_game(rngs.gen::<u64>());
+    }
+    score_sum / game_number as f64
+}
+#[allow(unused)]
+fn play_arena_game(seed: u64, first_ai: &dyn Fn(&GameState, u64) -> Option<Action>, second_ai: &dyn Fn(&GameState, u64) -> Option<Action>) -> GameState {
+    // first_ai を先手、second_ai を後手として一局対戦させ、終局した状態を返す。
+    // AI には手番ごとに seed から作ったシード値を渡すので、同じ seed の対局は再現できる。
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut state = GameState::new(rng.gen::<u64>());
+    while !state.is_done() {
+        let action = if state.is_first_player() { first_ai(&state, rng.gen::<u64>()) } else { second_ai(&state, rng.gen::<u64>()) };
+        match action {
+            Some(action) => state.advance(action),
+            None => break,
+        }
+    }
+    state
+}
+#[allow(unused)]
+pub fn arena(ai: &dyn Fn(&GameState, u64) -> Option<Action>, opponent_ai: &dyn Fn(&GameState, u64) -> Option<Action>, game_number: usize, seed: u64) -> ArenaResult {
+    // game_number 個のシードそれぞれで、ai と opponent_ai の先手と後手を入れ替えて二局ずつ対戦させる。
+    ArenaResult::play(game_number, seed, &|game_seed, is_first| {
+        let state = if is_first { play_arena_game(game_seed, ai, opponent_ai) } else { play_arena_game(game_seed, opponent_ai, ai) };
+        (state.get_first_player_score_for_win_rate(), state.get_first_player_score() as f64)
+    })
+}
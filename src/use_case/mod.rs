@@ -1,2 +1,5 @@
-mod single_player_game_with_context;
-mod single_player_game_with_no_context;
\ No newline at end of file
+pub mod single_player_game_with_context;
+pub mod single_player_game_with_no_context;
+pub mod two_player_alternate_game;
+pub mod two_player_imperfect_information_game;
+pub mod two_player_simultaneous_game;
\ No newline at end of file
@@ -1,9 +1,17 @@
 use rand::*;
 
+use crate::evaluation::{evaluate, game_seeds, EvaluationResult};
+use crate::game_record::GameRecord;
+use crate::search_observer::{NoObserver, SearchControl, SearchEvent, SearchObserver};
+use crate::search_statistics::SearchStatistics;
+use crate::statistics::{PairedComparison, ScoreSummary};
 use crate::time_keeper::TimeKeeper;
+use crate::tuning::{tune, Parameter, ParameterRange, TuningMethod, TuningResult};
+use crate::visualizer::{render_html, render_svg, GridFrame};
 
 const H: usize = 30;
 const W: usize = 40;
+const END_TURN: usize = 10;
 
 #[allow(unused)]
 #[derive(Clone, Copy)]
@@ -50,6 +58,23 @@ impl Board {
 enum Action {
     D(usize, usize),
 }
+#[allow(unused)]
+impl Action {
+    fn to_record(self) -> String {
+        // GameRecord に書く文字列。x が増える向きを D とする。
+        match self {
+            Action::D(1, 0) => "D",
+            Action::D(0, 1) => "R",
+            Action::D(0, _) => "L",
+            Action::D(_, _) => "U",
+        }
+        .to_string()
+    }
+
+    fn from_record(text: &str) -> Option<Self> {
+        ACTIONS.into_iter().find(|action| action.to_record() == text)
+    }
+}
 
 
 #[allow(unused)]
@@ -73,7 +98,7 @@ impl GameState {
     pub fn new(seed: u64) -> Self {
         // GameState をシード値を用いて作成する。
         let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
-        let end_turn = 10;
+        let end_turn = END_TURN;
         let mut board = Board::new(rng.gen::<u64>());
         let player = Player::new(rng.gen::<u64>());
         board.points[player.x][player.y] = 0;
@@ -126,6 +151,43 @@ impl GameState {
     fn evaliated_score(&mut self) {
         self.evaliated_score = self.game_score
     }
+
+    fn board_lines(&self) -> Vec<String> {
+        // GameRecord に書く盤面。各マスの点数を一文字で書き、player のいるマスは @ にする。
+        (0..H)
+            .map(|x| {
+                (0..W)
+                    .map(|y| if (x, y) == (self.player.x, self.player.y) { '@' } else { char::from_digit(self.board.points[x][y] as u32, 10).unwrap() })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn from_board_lines(lines: &[String], end_turn: usize) -> Result<Self, String> {
+        // board_lines で書いた盤面から、end_turn ターンで終わる GameState を作る。
+        if lines.len() != H {
+            return Err(format!("board must have {} lines but has {}", H, lines.len()));
+        }
+        let mut board = Board { points: [[0; W]; H] };
+        let mut players = Vec::new();
+        for (x, line) in lines.iter().enumerate() {
+            if line.chars().count() != W {
+                return Err(format!("board line {} must have {} cells", x, W));
+            }
+            for (y, c) in line.chars().enumerate() {
+                if c == '@' {
+                    players.push(Player { x, y });
+                } else {
+                    board.points[x][y] = c.to_digit(10).ok_or(format!("invalid cell `{}` at ({}, {})", c, x, y))? as isize;
+                }
+            }
+        }
+        // player は盤面にちょうど一人いる。
+        match players[..] {
+            [player] => Ok(Self::build(board, player, end_turn)),
+            _ => Err(format!("board must have exactly one player but has {}", players.len())),
+        }
+    }
 }
 
 impl PartialEq for GameState {
@@ -149,8 +211,7 @@ impl Ord for GameState {
 
 /// ここから先は、action を決定する方法を定める関数を作る。
 #[allow(unused)]
-fn random_action(state: &GameState) -> Option<Action> {
-    let mut rng = rand::thread_rng();
+fn random_action(state: &GameState, rng: &mut rand::rngs::StdRng) -> Option<Action> {
     let legal_actions = state.legal_actions();
     if legal_actions.is_empty() {
         return None;
@@ -174,12 +235,19 @@ fn greedy_action(state: &GameState) -> Option<Action> {
 }
 #[allow(unused)]
 fn beam_search_action(state: &GameState, beam_width: usize, beam_depth: usize) -> Option<Action> {
+    beam_search_action_with_observer(state, beam_width, beam_depth, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn beam_search_action_with_observer(state: &GameState, beam_width: usize, beam_depth: usize, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // 層を一つ処理するたびに observer に LayerCompleted を送り、Abort が返されたらそこまでの最善手を返す。
+    let start_time = std::time::Instant::now();
     let mut now_beam = std::collections::BinaryHeap::new();
     let mut best_state = state.clone();
     now_beam.push(*state);
     for t in 0..beam_depth {
         let mut next_beam = std::collections::BinaryHeap::new();
         for now_state in now_beam.iter().take(beam_width) {
+            statistics.expanded_node_number += 1;
             for &action in now_state.legal_actions().iter() {
                 let mut next_state = now_state.clone();
                 next_state.advance(action);
@@ -188,27 +256,39 @@ fn beam_search_action(state: &GameState, beam_width: usize, beam_depth: usize) -
                     next_state.first_action = Some(action);
                 }
                 next_beam.push(next_state);
+                statistics.generated_state_number += 1;
             }
         }
         now_beam = next_beam;
+        statistics.completed_layer_number += 1;
+        statistics.record_depth(t + 1);
         best_state = *now_beam.peek().unwrap();
-        if best_state.is_done() {
+        let control = observer.on_event(&SearchEvent::LayerCompleted { depth: t + 1, score: best_state.evaliated_score as f64 });
+        if best_state.is_done() || control == SearchControl::Abort {
             break;
         }
     }
+    statistics.finish(start_time);
     best_state.first_action
 }
 #[allow(unused)]
 fn chokudai_search_action(state: &GameState, beam_width: usize, beam_depth: usize, beam_number: usize) -> Option<Action> {
+    chokudai_search_action_with_observer(state, beam_width, beam_depth, beam_number, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn chokudai_search_action_with_observer(state: &GameState, beam_width: usize, beam_depth: usize, beam_number: usize, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // 全ての層を一巡するたびに observer に Iteration を送り、Abort が返されたらそこまでの最善手を返す。
+    let start_time = std::time::Instant::now();
     let mut beams = vec![std::collections::BinaryHeap::new(); beam_depth + 1];
     beams[0].push(*state);
-    for _ in 0..beam_number {
+    for iteration in 0..beam_number {
         for t in 0..beam_depth {
             let mut next_beam = std::mem::take(&mut beams[t + 1]);
             for now_state in beams[t].iter().take(beam_width) {
                 if now_state.is_done() {
                     break;
                 }
+                statistics.expanded_node_number += 1;
                 for &action in now_state.legal_actions().iter() {
                     let mut next_state = now_state.clone();
                     next_state.advance(action);
@@ -217,13 +297,33 @@ fn chokudai_search_action(state: &GameState, beam_width: usize, beam_depth: usiz
                         next_state.first_action = Some(action);
                     }
                     next_beam.push(next_state);
+                    statistics.generated_state_number += 1;
                 }
             }
             std::mem::swap(&mut beams[t + 1], &mut next_beam);
+            statistics.completed_layer_number += 1;
         }
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: chokudai_search_best_score(&beams) }) == SearchControl::Abort {
+            break;
+        }
+    }
+    chokudai_search_best_action(&beams, statistics, start_time)
+}
+#[allow(unused)]
+fn chokudai_search_best_score(beams: &[std::collections::BinaryHeap<GameState>]) -> f64 {
+    // 最も深い空でないビームの先頭の評価値を返す。
+    match beams.iter().rev().find_map(|beam| beam.peek()) {
+        Some(state) => state.evaliated_score as f64,
+        None => -INF as f64,
     }
-    for beam in beams.iter().rev() {
+}
+#[allow(unused)]
+fn chokudai_search_best_action(beams: &[std::collections::BinaryHeap<GameState>], statistics: &mut SearchStatistics, start_time: std::time::Instant) -> Option<Action> {
+    // 最も深い空でないビームの先頭の first_action を返す。
+    statistics.finish(start_time);
+    for (t, beam) in beams.iter().enumerate().rev() {
         if !beam.is_empty() {
+            statistics.record_depth(t);
             return beam.peek().unwrap().first_action;
         }
     }
@@ -231,16 +331,22 @@ fn chokudai_search_action(state: &GameState, beam_width: usize, beam_depth: usiz
 }
 #[allow(unused)]
 fn beam_search_action_with_time_threshold(state: &GameState, beam_width: usize, time_threshold: f64) -> Option<Action> {
+    beam_search_action_with_time_threshold_and_observer(state, beam_width, time_threshold, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn beam_search_action_with_time_threshold_and_observer(state: &GameState, beam_width: usize, time_threshold: f64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
     let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
     let mut now_beam = std::collections::BinaryHeap::new();
     let mut best_state = state.clone();
     now_beam.push(*state);
-    for t in 0.. {
+    'search: for t in 0.. {
         let mut next_beam = std::collections::BinaryHeap::new();
         for now_state in now_beam.iter().take(beam_width) {
             if time_keeper.is_time_over() {
-                return best_state.first_action;
+                break 'search;
             }
+            statistics.expanded_node_number += 1;
             for &action in now_state.legal_actions().iter() {
                 let mut next_state = now_state.clone();
                 next_state.advance(action);
@@ -249,28 +355,39 @@ fn beam_search_action_with_time_threshold(state: &GameState, beam_width: usize,
                     next_state.first_action = Some(action);
                 }
                 next_beam.push(next_state);
+                statistics.generated_state_number += 1;
             }
         }
         now_beam = next_beam;
+        statistics.completed_layer_number += 1;
+        statistics.record_depth(t + 1);
         best_state = *now_beam.peek().unwrap();
-        if best_state.is_done() {
+        let control = observer.on_event(&SearchEvent::LayerCompleted { depth: t + 1, score: best_state.evaliated_score as f64 });
+        if best_state.is_done() || control == SearchControl::Abort {
             break;
         }
     }
+    statistics.finish(start_time);
     best_state.first_action
 }
 #[allow(unused)]
 fn chokudai_search_action_with_time_threshold(state: &GameState, beam_width: usize, beam_depth: usize, time_threshold: f64) -> Option<Action> {
+    chokudai_search_action_with_time_threshold_and_observer(state, beam_width, beam_depth, time_threshold, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn chokudai_search_action_with_time_threshold_and_observer(state: &GameState, beam_width: usize, beam_depth: usize, time_threshold: f64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
     let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
     let mut beams = vec![std::collections::BinaryHeap::new(); beam_depth + 1];
     beams[0].push(*state);
-    for _ in 0.. {
+    for iteration in 0.. {
         for t in 0..beam_depth {
             let mut next_beam = std::mem::take(&mut beams[t + 1]);
             for now_state in beams[t].iter().take(beam_width) {
                 if now_state.is_done() {
                     break;
                 }
+                statistics.expanded_node_number += 1;
                 for &action in now_state.legal_actions().iter() {
                     let mut next_state = now_state.clone();
                     next_state.advance(action);
@@ -279,27 +396,54 @@ fn chokudai_search_action_with_time_threshold(state: &GameState, beam_width: usi
                         next_state.first_action = Some(action);
                     }
                     next_beam.push(next_state);
+                    statistics.generated_state_number += 1;
                 }
             }
             beams[t + 1] = next_beam;
+            statistics.completed_layer_number += 1;
         }
-        if time_keeper.is_time_over() {
+        if time_keeper.is_time_over() || observer.on_event(&SearchEvent::Iteration { iteration, score: chokudai_search_best_score(&beams) }) == SearchControl::Abort {
             break;
         }
     }
-    for beam in beams.iter().rev() {
-        if !beam.is_empty() {
-            return beam.peek().unwrap().first_action;
-        }
-    }
-    None
+    chokudai_search_best_action(&beams, statistics, start_time)
 }
 
  #[allow(unused)]
 pub fn play_game(seed: u64) -> isize {
+    play_game_with_beam_search(seed, 2, 5)
+}
+#[allow(unused)]
+pub fn play_game_with_beam_search(seed: u64, beam_width: usize, beam_depth: usize) -> isize {
+    let mut state = GameState::new(seed);
+    while !state.is_done() {
+        if let Some(action) = beam_search_action(&state, beam_width, beam_depth) {
+            state.advance(action);
+        } else {
+            return -INF;
+        }
+    }
+    state.game_score
+}
+#[allow(unused)]
+pub fn play_game_with_chokudai_search(seed: u64, beam_width: usize, beam_depth: usize, beam_number: usize) -> isize {
+    let mut state = GameState::new(seed);
+    while !state.is_done() {
+        if let Some(action) = chokudai_search_action(&state, beam_width, beam_depth, beam_number) {
+            state.advance(action);
+        } else {
+            return -INF;
+        }
+    }
+    state.game_score
+}
+#[allow(unused)]
+pub fn play_game_with_time_threshold(seed: u64, time_threshold: f64) -> isize {
+    // 一ゲームに time_threshold 秒を使い、残りのターンに等分して beam_search_action_with_time_threshold で進める。
     let mut state = GameState::new(seed);
+    let turn_time_threshold = time_threshold / (state.end_turn - state.turn).max(1) as f64;
     while !state.is_done() {
-        if let Some(action) = beam_search_action(&state, 2, 5) {
+        if let Some(action) = beam_search_action_with_time_threshold(&state, 2, turn_time_threshold) {
             state.advance(action);
         } else {
             return -INF;
@@ -308,11 +452,155 @@ pub fn play_game(seed: u64) -> isize {
     state.game_score
 }
 #[allow(unused)]
-pub fn test_ai_score(game_number: usize, seed: u64) -> f64 {
-    let mut rngs: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
-    let mut score_sum = 0;
-    for _ in 0..game_number {
-        score_sum += play_game(rngs.gen::<u64>()) as i128;
+pub fn play_game_record(seed: u64) -> GameRecord {
+    play_game_record_with(seed, &|state| beam_search_action(state, 2, 5))
+}
+#[allow(unused)]
+fn play_game_record_with(seed: u64, choose_action: &dyn Fn(&GameState) -> Option<Action>) -> GameRecord {
+    // choose_action でゲームを進め、初期盤面と各ターンの action とスコアを記録する。action が無くなったらそこで記録を終える。
+    let mut state = GameState::new(seed);
+    let mut record = GameRecord::new(seed, state.board_lines());
+    while !state.is_done() {
+        let Some(action) = choose_action(&state) else {
+            break;
+        };
+        state.advance(action);
+        record.push(action.to_record(), state.game_score);
+    }
+    record
+}
+#[allow(unused)]
+pub fn replay_game_record(record: &GameRecord) -> Result<isize, String> {
+    // record の初期盤面から action を advance で順に適用し、各ターンのスコアが記録と一致することを確かめて最終スコアを返す。
+    Ok(replay_game_states(record)?.last().unwrap().game_score)
+}
+#[allow(unused)]
+fn replay_game_states(record: &GameRecord) -> Result<Vec<GameState>, String> {
+    // replay_game_record と同じ確認をしながら、初期状態と各ターンの後の状態を返す。終了ターンは記録の長さではなくゲームの値を使う。
+    let mut state = GameState::from_board_lines(&record.initial_board, END_TURN)?;
+    let mut states = vec![state];
+    for (turn, (action, &score)) in record.actions.iter().zip(record.scores.iter()).enumerate() {
+        if state.is_done() {
+            return Err(format!("turn {}: game already ended after {} turns", turn, END_TURN));
+        }
+        let action = Action::from_record(action).ok_or(format!("turn {}: unknown action `{}`", turn, action))?;
+        if !state.legal_actions().into_iter().any(|legal_action| legal_action.to_record() == action.to_record()) {
+            return Err(format!("turn {}: illegal action `{}`", turn, action.to_record()));
+        }
+        state.advance(action);
+        if state.game_score != score {
+            return Err(format!("turn {}: recorded score {} but replayed score {}", turn, score, state.game_score));
+        }
+        states.push(state);
     }
-    score_sum as f64 / game_number as f64
-}
\ No newline at end of file
+    Ok(states)
+}
+#[allow(unused)]
+fn game_record_frames(record: &GameRecord) -> Result<Vec<GridFrame>, String> {
+    // record を再生し、各ターンの盤面とそれまでに通ったマスを GridFrame にする。
+    let states = replay_game_states(record)?;
+    let mut path = Vec::with_capacity(states.len());
+    Ok(states
+        .iter()
+        .map(|state| {
+            path.push((state.player.x, state.player.y));
+            GridFrame {
+                turn: state.turn,
+                score: state.game_score,
+                cells: state.board.points.iter().map(|points| points.to_vec()).collect(),
+                path: path.clone(),
+            }
+        })
+        .collect())
+}
+#[allow(unused)]
+pub fn render_game_record_svg(record: &GameRecord) -> Result<String, String> {
+    // 最後のターンの盤面と player の通った道を SVG にする。
+    let frames = game_record_frames(record)?;
+    Ok(render_svg(frames.last().unwrap(), 9))
+}
+#[allow(unused)]
+pub fn render_game_record_html(record: &GameRecord) -> Result<String, String> {
+    // ターンごとの盤面を切り替えて見られる HTML にする。
+    let frames = game_record_frames(record)?;
+    Ok(render_html(&frames, 9, &format!("maze seed {}", record.seed)))
+}
+#[allow(unused)]
+pub fn save_game_record_svg(record: &GameRecord, path: &str) -> Result<(), String> {
+    // render_game_record_svg の結果を path に書き出す。
+    let svg = render_game_record_svg(record)?;
+    std::fs::write(path, svg).map_err(|error| format!("{}: {}", path, error))
+}
+#[allow(unused)]
+pub fn save_game_record_html(record: &GameRecord, path: &str) -> Result<(), String> {
+    // render_game_record_html の結果を path に書き出す。ブラウザで開くとターンごとに再生できる。
+    let html = render_game_record_html(record)?;
+    std::fs::write(path, html).map_err(|error| format!("{}: {}", path, error))
+}
+#[allow(unused)]
+pub fn test_ai_score(game_number: usize, seed: u64) -> ScoreSummary {
+    test_ai_score_with(&play_game, game_number, seed)
+}
+#[allow(unused)]
+pub fn test_ai_score_with(play_game: &dyn Fn(u64) -> isize, game_number: usize, seed: u64) -> ScoreSummary {
+    // play_game を game_seeds で seed から作ったシードの列で動かす。
+    ScoreSummary::build(game_seeds(game_number, seed).into_iter().map(|seed| (seed, play_game(seed))).collect())
+}
+#[allow(unused)]
+pub fn test_ai_score_in_parallel(game_number: usize, seed: u64, thread_number: usize, time_threshold: f64) -> EvaluationResult {
+    evaluate(&game_seeds(game_number, seed), thread_number, time_threshold, &play_game_with_time_threshold)
+}
+#[allow(unused)]
+pub fn compare_ai_score(play_game: &dyn Fn(u64) -> isize, other_play_game: &dyn Fn(u64) -> isize, game_number: usize, seed: u64) -> PairedComparison {
+    // 二つの play_game を同じシードの列で動かし、スコアの差を対応のある比較で評価する。
+    PairedComparison::build(game_seeds(game_number, seed).into_iter().map(|seed| (seed, play_game(seed), other_play_game(seed))).collect())
+}
+#[allow(unused)]
+pub fn tune_beam_search(method: TuningMethod, game_number: usize, seed: u64) -> Result<TuningResult, String> {
+    // beam_search_action の beam_width と beam_depth を、test_ai_score の平均スコアが高くなるように選ぶ。
+    let parameters = [Parameter::build("beam_width", ParameterRange::Int(1, 10))?, Parameter::build("beam_depth", ParameterRange::Int(1, 10))?];
+    let objective = |configuration: &[f64], game_number: usize| {
+        let (beam_width, beam_depth) = (configuration[0] as usize, configuration[1] as usize);
+        test_ai_score_with(&|seed| play_game_with_beam_search(seed, beam_width, beam_depth), game_number, seed).mean
+    };
+    tune(&parameters, &objective, method, game_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_gives_the_recorded_final_score() {
+        for seed in 0..10 {
+            let record = play_game_record(seed);
+            assert_eq!(replay_game_record(&record), Ok(record.final_score().unwrap()));
+            assert_eq!(record.final_score(), Some(play_game(seed)));
+            let record = GameRecord::from_text(&record.to_text()).unwrap();
+            assert_eq!(replay_game_record(&record), Ok(record.final_score().unwrap()));
+        }
+    }
+
+    #[test]
+    fn board_lines_need_exactly_one_player() {
+        let lines = GameState::new(0).board_lines();
+        assert!(GameState::from_board_lines(&lines, END_TURN).is_ok());
+        let without_player: Vec<String> = lines.iter().map(|line| line.replace('@', "0")).collect();
+        assert!(GameState::from_board_lines(&without_player, END_TURN).is_err());
+        let mut with_two_players = lines.clone();
+        with_two_players[0].replace_range(0..1, "@");
+        with_two_players[H - 1].replace_range(W - 1..W, "@");
+        assert!(GameState::from_board_lines(&with_two_players, END_TURN).is_err());
+    }
+
+    #[test]
+    fn replay_rejects_actions_after_the_end_turn() {
+        let mut record = play_game_record(0);
+        assert_eq!(record.actions.len(), END_TURN);
+        let state = replay_game_states(&record).unwrap().pop().unwrap();
+        assert!(state.is_done());
+        let (action, score) = (record.actions[0].clone(), record.final_score().unwrap());
+        record.push(action, score);
+        assert!(replay_game_record(&record).is_err());
+    }
+}
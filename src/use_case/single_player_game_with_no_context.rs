@@ -1,8 +1,16 @@
 use rand::*;
 
+use crate::evaluation::{evaluate, game_seeds, EvaluationResult};
+use crate::random::Xorshift;
+use crate::search_observer::{NoObserver, SearchControl, SearchEvent, SearchObserver};
+use crate::search_statistics::{SearchStatistics, ACCEPTANCE_RATE_POINT_NUMBER};
+use crate::statistics::{PairedComparison, ScoreSummary};
+use crate::time_keeper::TimeKeeper;
+use crate::tuning::{tune, Parameter, ParameterRange, TuningMethod, TuningResult};
+
 #[allow(unused)]
 #[derive(Clone, Copy)]
-struct Player {
+pub struct Player {
     pos: [(usize, usize); 3],
 }
 #[allow(unused)]
@@ -16,17 +24,63 @@ impl Player {
     }
 
     fn transition(&mut self, seed: u64) {
-        let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
-        let idx = rng.gen::<usize>() % 3;
-        self.pos[idx] = (rng.gen::<usize>() % H, rng.gen::<usize>() % W);
+        let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+        let idx = rng.next_index(3);
+        self.pos[idx] = (rng.next_index(H), rng.next_index(W));
+    }
+
+    fn transition_with(&mut self, neighborhood: Neighborhood, seed: u64) {
+        let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+        match neighborhood {
+            Neighborhood::Relocate => {
+                let idx = rng.next_index(3);
+                self.pos[idx] = (rng.next_index(H), rng.next_index(W));
+            }
+            Neighborhood::Shift => {
+                let idx = rng.next_index(3);
+                let (x, y) = self.pos[idx];
+                let Action::D(dx, dy) = ACTIONS[rng.next_index(ACTIONS.len())];
+                let (x, y) = (x.wrapping_add(dx), y.wrapping_add(dy));
+                if x < H && y < W {
+                    self.pos[idx] = (x, y);
+                }
+            }
+            Neighborhood::RelocateTwo => {
+                let skip = rng.next_index(3);
+                for idx in (0..3).filter(|&idx| idx != skip) {
+                    self.pos[idx] = (rng.next_index(H), rng.next_index(W));
+                }
+            }
+        }
+    }
+
+    fn kick(&mut self, seed: u64) {
+        // transition より大きな変化として、ロボット二台を盤面上の好きな位置に移す。
+        self.transition_with(Neighborhood::RelocateTwo, seed);
+    }
+
+    fn crossover(&self, other: &Player, seed: u64) -> Self {
+        let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+        // 各ロボットの位置を self と other のどちらかから受け継ぐ。
+        let mut pos = self.pos;
+        for (pos, &other_pos) in pos.iter_mut().zip(other.pos.iter()) {
+            if rng.gen::<bool>() {
+                *pos = other_pos;
+            }
+        }
+        Self {
+            pos,
+        }
     }
 }
 
 const H: usize = 30;
 const W: usize = 40;
+// transition_with_delta が差分計算を行える最大のターン数。
+const END_TURN: usize = 10;
 #[derive(Clone, Copy)]
 #[allow(unused)]
-struct Board {
+pub struct Board {
     // board の状態を表す。
     points: [[isize; W]; H],
 }
@@ -58,9 +112,38 @@ const ACTIONS: [Action; 4] = [Action::D(1, 0), Action::D(!0, 0), Action::D(0, 1)
 #[allow(unused)]
 const INF: isize = std::isize::MAX / 2;
 
+#[allow(unused)]
 #[derive(Clone, Copy)]
+pub enum Neighborhood {
+    // ロボット一台を盤面上の好きな位置に移す。
+    Relocate,
+    // ロボット一台を隣のマスに動かす。
+    Shift,
+    // ロボット二台を盤面上の好きな位置に移す。
+    RelocateTwo,
+}
+#[allow(unused)]
+impl Neighborhood {
+    fn name(&self) -> &'static str {
+        match *self {
+            Neighborhood::Relocate => "relocate",
+            Neighborhood::Shift => "shift",
+            Neighborhood::RelocateTwo => "relocate_two",
+        }
+    }
+}
+// adaptive_simulated_annealing が選択に用いる近傍全体。
+#[allow(unused)]
+const NEIGHBORHOODS: [Neighborhood; 3] = [Neighborhood::Relocate, Neighborhood::Shift, Neighborhood::RelocateTwo];
+// 近傍の直近の成功率を更新するときの減衰率と、成功率が低い近傍にも残す選択の重み。
+#[allow(unused)]
+const NEIGHBORHOOD_DECAY: f64 = 0.95;
 #[allow(unused)]
-struct GameState {
+const MIN_NEIGHBORHOOD_WEIGHT: f64 = 0.05;
+
+#[derive(Clone, Copy)]
+#[allow(unused)]
+pub struct GameState {
     board: Board,
     initial_board: Board,
     turn: usize,
@@ -68,15 +151,19 @@ struct GameState {
     game_score: isize,
     evaliated_score: isize,
     end_turn: usize,
+    // 各ロボットを単独で動かしたときの経路とスコア。transition_with_delta が用いる。
+    robot_paths: [[(usize, usize); END_TURN + 1]; 3],
+    robot_scores: [isize; 3],
+    is_robot_cached: [bool; 3],
 }
 #[allow(unused)]
 impl GameState {
     pub fn new(seed: u64) -> Self {
         // GameState をシード値を用いて作成する。
         let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
-        let board = Board::new(0);
-        let player = Player::new(0);
-        let end_turn = 10;
+        let board = Board::new(rng.gen::<u64>());
+        let player = Player::new(rng.gen::<u64>());
+        let end_turn = END_TURN;
         Self {
             board: board,
             initial_board: board.clone(),
@@ -85,6 +172,9 @@ impl GameState {
             game_score: 0,
             evaliated_score: 0,
             end_turn: end_turn,
+            robot_paths: [[(0, 0); END_TURN + 1]; 3],
+            robot_scores: [0; 3],
+            is_robot_cached: [false; 3],
         }
     }
     pub fn build(board: Board, player: Player, end_turn: usize) -> Self {
@@ -97,24 +187,138 @@ impl GameState {
             game_score: 0,
             evaliated_score: 0,
             end_turn: end_turn,
+            robot_paths: [[(0, 0); END_TURN + 1]; 3],
+            robot_scores: [0; 3],
+            is_robot_cached: [false; 3],
         }
     }
 
-    pub fn init(&mut self) {
+    pub fn init(&mut self, rng: &mut Xorshift) {
         // player の状態をランダムに初期化する。
-        let mut rng = rand::thread_rng();
         self.player = Player::new(rng.gen::<u64>());
         for &(x, y) in self.player.pos.iter() {
             self.board.points[x][y] = 0;
         }
     }
 
-    pub fn transition(&mut self) {
+    pub fn transition(&mut self, rng: &mut Xorshift) {
         // player の状態を一つ遷移させ、盤面を初期化する。
-        let mut rng = rand::thread_rng();
-        self.board = self.initial_board.clone();
+        self.player.transition(rng.gen::<u64>());
+        self.reset();
+    }
+
+    fn reset(&mut self) {
+        // player はそのままに、盤面を初期化する。
+        self.board = self.initial_board;
         self.turn = 0;
+        for &(x, y) in self.player.pos.iter() {
+            self.board.points[x][y] = 0;
+        }
+        self.game_score = 0;
+        self.evaliated_score = 0;
+    }
+
+    pub fn transition_with_delta(&mut self, rng: &mut Xorshift) -> Option<isize> {
+        // player の状態を一つ遷移させ、ロボット同士が干渉しない場合は、各ロボットを単独で動かしたスコアの和を返す。
+        // このとき game は終了したものとして扱い、盤面は再現しない。
+        // 干渉し得る場合は、transition と同じく盤面を初期化して None を返す。
         self.player.transition(rng.gen::<u64>());
+        self.evaluate_with_delta()
+    }
+
+    pub fn transition_with_neighborhood_and_delta(&mut self, neighborhood: Neighborhood, rng: &mut Xorshift) -> Option<isize> {
+        // transition_with_delta と同じだが、player の状態を neighborhood で遷移させる。
+        self.player.transition_with(neighborhood, rng.gen::<u64>());
+        self.evaluate_with_delta()
+    }
+
+    fn evaluate_with_delta(&mut self) -> Option<isize> {
+        // player を動かした後に呼び、位置の変わったロボットの経路だけを計算し直す。
+        if self.end_turn <= END_TURN {
+            for idx in 0..3 {
+                if !self.is_robot_cached[idx] || self.robot_paths[idx][0] != self.player.pos[idx] {
+                    self.cache_robot(idx);
+                }
+            }
+            if self.is_robots_independent() {
+                self.turn = self.end_turn;
+                self.game_score = self.robot_scores.iter().sum();
+                self.evaliated_score = 0;
+                return Some(self.game_score);
+            }
+        }
+        self.reset();
+        None
+    }
+
+    fn cache_robot(&mut self, idx: usize) {
+        // idx 番目のロボットだけを盤面に置いて advance と同じ規則で動かし、経路とスコアを記録する。
+        // 自分がすでに通ったマスは 0 になっている。
+        let mut visited = [0u64; H];
+        let path = &mut self.robot_paths[idx];
+        path[0] = self.player.pos[idx];
+        visited[path[0].0] |= 1 << path[0].1;
+        let mut score = 0;
+        for t in 0..self.end_turn {
+            let (x, y) = path[t];
+            let mut best_point = -INF;
+            let mut best_pos = (x, y);
+            for &Action::D(dx, dy) in ACTIONS.iter() {
+                let (nx, ny) = (x.wrapping_add(dx), y.wrapping_add(dy));
+                if nx < H && ny < W {
+                    let point = if visited[nx] >> ny & 1 == 1 { 0 } else { self.initial_board.points[nx][ny] };
+                    if point > best_point {
+                        best_point = point;
+                        best_pos = (nx, ny);
+                    }
+                }
+            }
+            path[t + 1] = best_pos;
+            visited[best_pos.0] |= 1 << best_pos.1;
+            score += best_point;
+        }
+        self.robot_scores[idx] = score;
+        self.is_robot_cached[idx] = true;
+    }
+
+    fn is_robots_independent(&self) -> bool {
+        // どのロボットも、他のロボットが通るマスを一度も通らず、隣から見ることもなければ、
+        // 各ロボットの動きは単独で動かしたときと変わらない。
+        let paths = self.robot_paths.each_ref().map(|path| &path[..=self.end_turn]);
+        let bounds = paths.map(|path| {
+            path.iter().fold((H, 0, W, 0), |(min_x, max_x, min_y, max_y), &(x, y)| (min_x.min(x), max_x.max(x), min_y.min(y), max_y.max(y)))
+        });
+        for i in 0..3 {
+            for j in i + 1..3 {
+                // 経路を囲む長方形同士が二マス以上離れていれば、干渉しない。
+                let (i_min_x, i_max_x, i_min_y, i_max_y) = bounds[i];
+                let (j_min_x, j_max_x, j_min_y, j_max_y) = bounds[j];
+                if i_max_x + 1 < j_min_x || j_max_x + 1 < i_min_x || i_max_y + 1 < j_min_y || j_max_y + 1 < i_min_y {
+                    continue;
+                }
+                for &(ix, iy) in paths[i].iter() {
+                    for &(jx, jy) in paths[j].iter() {
+                        if ix.abs_diff(jx) + iy.abs_diff(jy) <= 1 {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    pub fn kick(&mut self, rng: &mut Xorshift) {
+        // transition より大きく player の状態を変化させ、盤面を初期化する。
+        self.player.kick(rng.gen::<u64>());
+        self.reset();
+    }
+
+    pub fn transition_with(&mut self, neighborhood: Neighborhood, rng: &mut Xorshift) {
+        // player の状態を neighborhood で一つ遷移させ、盤面を初期化する。
+        self.board = self.initial_board;
+        self.turn = 0;
+        self.player.transition_with(neighborhood, rng.gen::<u64>());
         for &(x, y) in self.player.pos.iter() {
             self.board.points[x][y] = 0;
         }
@@ -122,6 +326,20 @@ impl GameState {
         self.evaliated_score = 0;
     }
 
+    pub fn crossover(&self, other: &GameState, rng: &mut Xorshift) -> Self {
+        // self と other の player を交叉させた状態を作り、盤面を初期化する。
+        let mut state = *self;
+        state.board = self.initial_board;
+        state.turn = 0;
+        state.player = self.player.crossover(&other.player, rng.gen::<u64>());
+        for &(x, y) in state.player.pos.iter() {
+            state.board.points[x][y] = 0;
+        }
+        state.game_score = 0;
+        state.evaliated_score = 0;
+        state
+    }
+
     fn is_done(&self) -> bool {
         // game が終了しているかどうかを返す。
         self.turn == self.end_turn
@@ -154,7 +372,7 @@ impl GameState {
         self.turn += 1;
     }
 
-    fn get_score(&mut self) -> isize {
+    pub fn get_score(&mut self) -> isize {
         while !self.is_done() {
             self.advance();
         }
@@ -162,56 +380,838 @@ impl GameState {
     }
 }
 #[allow(unused)]
-fn random(mut state: GameState) -> GameState {
-    state.init();
+fn random(mut state: GameState, seed: u64) -> GameState {
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    state.init(&mut rng);
     state
 }
 #[allow(unused)]
-fn hill_climb(mut state: GameState, number: usize) -> GameState {
-    state.init();
+fn hill_climb(state: GameState, number: usize, seed: u64) -> GameState {
+    hill_climb_with_observer(state, number, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn hill_climb_with_observer(mut state: GameState, number: usize, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    let start_time = std::time::Instant::now();
+    statistics.set_acceptance_window_if_unset(number / ACCEPTANCE_RATE_POINT_NUMBER);
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    state.init(&mut rng);
+    let state = local_search_with_observer(state, number, &mut rng, statistics, observer).0;
+    statistics.finish(start_time);
+    state
+}
+#[allow(unused)]
+fn local_search(state: GameState, number: usize, rng: &mut Xorshift) -> (GameState, isize) {
+    local_search_with_observer(state, number, rng, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn local_search_with_observer(mut state: GameState, number: usize, rng: &mut Xorshift, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> (GameState, isize) {
+    // state から始めて、スコアが改善する遷移だけを受理することを number 回繰り返す。
+    // 改善するたびに observer に NewBest を、遷移を一回試すたびに Iteration を送り、Abort が返されたらそこで打ち切る。
     let mut best_score = state.get_score();
-    for _ in 0..number {
+    for iteration in 0..number {
         let mut now_state = state.clone();
-        now_state.transition();
-        let now_score = now_state.get_score();
+        let now_score = match now_state.transition_with_delta(rng) {
+            Some(score) => score,
+            None => now_state.get_score(),
+        };
+        statistics.generated_state_number += 1;
+        statistics.record_acceptance(now_score > best_score);
         if now_score > best_score {
             best_score = now_score;
             std::mem::swap(&mut state, &mut now_state);
+            if observer.on_event(&SearchEvent::NewBest { iteration, score: best_score as f64 }) == SearchControl::Abort {
+                break;
+            }
+        }
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: best_score as f64 }) == SearchControl::Abort {
+            break;
         }
     }
-    state
+    (state, best_score)
+}
+#[allow(unused)]
+fn simulated_annealing(state: GameState, number: usize, start_temp: f64, end_temp: f64, seed: u64) -> GameState {
+    simulated_annealing_with_observer(state, number, start_temp, end_temp, seed, &mut SearchStatistics::default(), &mut NoObserver)
 }
 #[allow(unused)]
-fn simulated_annealing(mut state: GameState, number: usize, start_temp: f64, end_temp: f64) -> GameState {
-    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(0);
-    state.init();
+fn simulated_annealing_with_observer(mut state: GameState, number: usize, start_temp: f64, end_temp: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    // 遷移を一回試すたびに observer に TemperatureStep と Iteration を、これまでの最高スコアを更新するたびに NewBest を送る。
+    // Abort が返されたらそこで打ち切り、その時点の状態を返す。
+    let start_time = std::time::Instant::now();
+    statistics.set_acceptance_window_if_unset(number / ACCEPTANCE_RATE_POINT_NUMBER);
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    state.init(&mut rng);
     let mut best_score = state.get_score();
+    let mut max_score = best_score;
     for i in 0..number {
-        let mut now_state = state.clone();
-        now_state.transition();
-        let now_score = now_state.get_score();
         let temp = start_temp + (end_temp - start_temp) * (i as f64 / number as f64);
+        if observer.on_event(&SearchEvent::TemperatureStep { iteration: i, temperature: temp }) == SearchControl::Abort {
+            break;
+        }
+        let mut now_state = state.clone();
+        let now_score = match now_state.transition_with_delta(&mut rng) {
+            Some(score) => score,
+            None => now_state.get_score(),
+        };
+        statistics.generated_state_number += 1;
         let probability = ((now_score - best_score) as f64 / temp).exp();
-        if now_score > best_score || probability > rng.gen::<u8>() as f64 / std::u8::MAX as f64 {
+        let is_accepted = now_score > best_score || probability > rng.next_f64();
+        statistics.record_acceptance(is_accepted);
+        if is_accepted {
             best_score = now_score;
             std::mem::swap(&mut state, &mut now_state);
         }
+        if best_score > max_score {
+            max_score = best_score;
+            if observer.on_event(&SearchEvent::NewBest { iteration: i, score: max_score as f64 }) == SearchControl::Abort {
+                break;
+            }
+        }
+        if observer.on_event(&SearchEvent::Iteration { iteration: i, score: best_score as f64 }) == SearchControl::Abort {
+            break;
+        }
     }
+    statistics.finish(start_time);
     state
 }
+#[allow(unused)]
+fn temperature_for_acceptance(deltas: &[f64], acceptance: f64) -> f64 {
+    // 悪化量 deltas の遷移を受理する確率の平均が acceptance になる温度を、温度の対数の二分探索で求める。
+    let acceptance = acceptance.clamp(1e-9, 1.0 - 1e-9);
+    let average_acceptance = |temp: f64| deltas.iter().map(|delta| (-delta / temp).exp()).sum::<f64>() / deltas.len() as f64;
+    let (mut low, mut high) = (1e-9f64.ln(), 1e9f64.ln());
+    for _ in 0..100 {
+        let middle = (low + high) / 2.0;
+        if average_acceptance(middle.exp()) < acceptance {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    ((low + high) / 2.0).exp()
+}
+#[allow(unused)]
+fn estimate_temperatures(mut state: GameState, sample_number: usize, start_acceptance: f64, end_acceptance: f64, seed: u64) -> (f64, f64) {
+    // random な状態から transition を sample_number 回続けてスコアの変化を集め、
+    // スコアが悪化する遷移の受理率の平均が、最初は start_acceptance、最後は end_acceptance になる (start_temp, end_temp) を返す。
+    // 悪化する遷移が一度もなければ、どの温度でも同じなので (1.0, 1.0) を返す。
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    state.init(&mut rng);
+    let mut now_score = state.get_score();
+    let mut deltas = Vec::with_capacity(sample_number);
+    for _ in 0..sample_number {
+        let next_score = match state.transition_with_delta(&mut rng) {
+            Some(score) => score,
+            None => state.get_score(),
+        };
+        if next_score < now_score {
+            deltas.push((now_score - next_score) as f64);
+        }
+        now_score = next_score;
+    }
+    if deltas.is_empty() {
+        return (1.0, 1.0);
+    }
+    (temperature_for_acceptance(&deltas, start_acceptance), temperature_for_acceptance(&deltas, end_acceptance))
+}
+#[allow(unused)]
+fn simulated_annealing_with_estimated_temperatures(state: GameState, number: usize, start_acceptance: f64, end_acceptance: f64, seed: u64) -> GameState {
+    // estimate_temperatures で求めた温度で simulated_annealing を行う。
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let (start_temp, end_temp) = estimate_temperatures(state, (number / 10).max(100), start_acceptance, end_acceptance, rng.gen::<u64>());
+    simulated_annealing(state, number, start_temp, end_temp, rng.gen::<u64>())
+}
+
+#[allow(unused)]
+#[derive(Clone, Copy)]
+struct NeighborhoodStatistics {
+    // adaptive_simulated_annealing で、近傍一つごとの試行回数と受理回数を記録する。
+    neighborhood: Neighborhood,
+    tried: usize,
+    accepted: usize,
+    improved: usize,
+    recent_success_rate: f64,
+}
+#[allow(unused)]
+impl NeighborhoodStatistics {
+    fn new(neighborhood: Neighborhood) -> Self {
+        // 最初はどの近傍も一度は選ばれるよう、直近の成功率を 1 から始める。
+        Self {
+            neighborhood,
+            tried: 0,
+            accepted: 0,
+            improved: 0,
+            recent_success_rate: 1.0,
+        }
+    }
+
+    fn weight(&self) -> f64 {
+        self.recent_success_rate.max(MIN_NEIGHBORHOOD_WEIGHT)
+    }
+
+    fn record(&mut self, is_accepted: bool, is_improved: bool) {
+        self.tried += 1;
+        self.accepted += is_accepted as usize;
+        self.improved += is_improved as usize;
+        let success = if is_accepted { 1.0 } else { 0.0 };
+        self.recent_success_rate = NEIGHBORHOOD_DECAY * self.recent_success_rate + (1.0 - NEIGHBORHOOD_DECAY) * success;
+    }
+
+    pub fn acceptance_rate(&self) -> f64 {
+        if self.tried == 0 {
+            return 0.0;
+        }
+        self.accepted as f64 / self.tried as f64
+    }
+}
+impl std::fmt::Display for NeighborhoodStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: tried {}, accepted {} ({:.1}%), improved {}",
+            self.neighborhood.name(),
+            self.tried,
+            self.accepted,
+            self.acceptance_rate() * 100.0,
+            self.improved
+        )
+    }
+}
+#[allow(unused)]
+fn select_neighborhood(statistics: &[NeighborhoodStatistics], rng: &mut Xorshift) -> usize {
+    // 直近の成功率に比例した確率で近傍を選ぶ。
+    let total_weight = statistics.iter().map(|statistic| statistic.weight()).sum::<f64>();
+    let mut r = rng.next_f64() * total_weight;
+    for (k, statistic) in statistics.iter().enumerate() {
+        if r < statistic.weight() {
+            return k;
+        }
+        r -= statistic.weight();
+    }
+    statistics.len() - 1
+}
+#[allow(unused)]
+fn adaptive_simulated_annealing(state: GameState, number: usize, start_temp: f64, end_temp: f64, seed: u64) -> (GameState, Vec<NeighborhoodStatistics>) {
+    adaptive_simulated_annealing_with_observer(state, number, start_temp, end_temp, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn adaptive_simulated_annealing_with_observer(mut state: GameState, number: usize, start_temp: f64, end_temp: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> (GameState, Vec<NeighborhoodStatistics>) {
+    // NEIGHBORHOODS の中から、直近の受理率が高い近傍ほど選ばれやすくして焼きなましを行う。
+    // 返り値の二つ目は近傍ごとの試行回数と受理回数。observer には simulated_annealing と同じ event を送る。
+    let start_time = std::time::Instant::now();
+    statistics.set_acceptance_window_if_unset(number / ACCEPTANCE_RATE_POINT_NUMBER);
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let mut neighborhood_statistics = NEIGHBORHOODS.iter().map(|&neighborhood| NeighborhoodStatistics::new(neighborhood)).collect::<Vec<_>>();
+    state.init(&mut rng);
+    let mut best_score = state.get_score();
+    if neighborhood_statistics.is_empty() {
+        statistics.finish(start_time);
+        return (state, neighborhood_statistics);
+    }
+    let mut max_score = best_score;
+    for i in 0..number {
+        let temp = start_temp + (end_temp - start_temp) * (i as f64 / number as f64);
+        if observer.on_event(&SearchEvent::TemperatureStep { iteration: i, temperature: temp }) == SearchControl::Abort {
+            break;
+        }
+        let k = select_neighborhood(&neighborhood_statistics, &mut rng);
+        let mut now_state = state;
+        let now_score = match now_state.transition_with_neighborhood_and_delta(neighborhood_statistics[k].neighborhood, &mut rng) {
+            Some(score) => score,
+            None => now_state.get_score(),
+        };
+        statistics.generated_state_number += 1;
+        let probability = ((now_score - best_score) as f64 / temp).exp();
+        let is_accepted = now_score > best_score || probability > rng.next_f64();
+        neighborhood_statistics[k].record(is_accepted, now_score > best_score);
+        statistics.record_acceptance(is_accepted);
+        if is_accepted {
+            best_score = now_score;
+            std::mem::swap(&mut state, &mut now_state);
+        }
+        if best_score > max_score {
+            max_score = best_score;
+            if observer.on_event(&SearchEvent::NewBest { iteration: i, score: max_score as f64 }) == SearchControl::Abort {
+                break;
+            }
+        }
+        if observer.on_event(&SearchEvent::Iteration { iteration: i, score: best_score as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    (state, neighborhood_statistics)
+}
+
+#[allow(unused)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Acceptance {
+    // iterated_local_search で、kick して局所探索した状態を次の出発点として受理する規則。
+    // スコアが改善したときだけ受理する。
+    Better,
+    // スコアが悪化しなければ受理する。
+    BetterOrEqual,
+    // 常に受理する。
+    Always,
+    // これまでの最良スコアからの悪化が指定した値以下なら受理する。
+    Threshold(isize),
+}
+#[allow(unused)]
+impl Acceptance {
+    fn is_accepted(&self, now_score: isize, candidate_score: isize, best_score: isize) -> bool {
+        match *self {
+            Acceptance::Better => candidate_score > now_score,
+            Acceptance::BetterOrEqual => candidate_score >= now_score,
+            Acceptance::Always => true,
+            Acceptance::Threshold(threshold) => candidate_score >= best_score - threshold,
+        }
+    }
+}
+impl std::str::FromStr for Acceptance {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // コマンドラインで指定する名前。Threshold は `threshold:<悪化の許容量>` と書く。
+        match s {
+            "better" => Ok(Acceptance::Better),
+            "better-or-equal" => Ok(Acceptance::BetterOrEqual),
+            "always" => Ok(Acceptance::Always),
+            _ => match s.strip_prefix("threshold:") {
+                Some(threshold) => threshold.parse().map(Acceptance::Threshold).map_err(|_| format!("invalid threshold `{}`", threshold)),
+                None => Err(format!("unknown acceptance `{}`", s)),
+            },
+        }
+    }
+}
+#[allow(unused)]
+#[allow(clippy::too_many_arguments)]
+fn iterated_local_search_step(now: &mut (GameState, isize), best: &mut (GameState, isize), local_search_number: usize, kick_number: usize, acceptance: Acceptance, rng: &mut Xorshift, statistics: &mut SearchStatistics) -> bool {
+    // now を kick_number 回 kick してから局所探索し、acceptance に従って now を更新する。best が更新されたら true を返す。
+    // kick_number は kick の強さで、局所最適から離れる距離を決める。
+    let mut candidate_state = now.0;
+    for _ in 0..kick_number.max(1) {
+        candidate_state.kick(rng);
+    }
+    let candidate = local_search_with_observer(candidate_state, local_search_number, rng, statistics, &mut NoObserver);
+    let is_improved = candidate.1 > best.1;
+    if is_improved {
+        *best = candidate;
+    }
+    if acceptance.is_accepted(now.1, candidate.1, best.1) {
+        *now = candidate;
+    }
+    is_improved
+}
+#[allow(unused)]
+fn iterated_local_search(state: GameState, local_search_number: usize, kick_number: usize, iteration_number: usize, acceptance: Acceptance, seed: u64) -> GameState {
+    iterated_local_search_with_observer(state, local_search_number, kick_number, iteration_number, acceptance, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn iterated_local_search_with_observer(mut state: GameState, local_search_number: usize, kick_number: usize, iteration_number: usize, acceptance: Acceptance, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    // 受理率は局所探索の中の遷移について記録する。
+    // kick を一回行うたびに observer に Iteration を、最良スコアが更新されたら NewBest を送り、Abort が返されたらそこで打ち切る。
+    let start_time = std::time::Instant::now();
+    statistics.set_acceptance_window_if_unset((iteration_number + 1) * local_search_number / ACCEPTANCE_RATE_POINT_NUMBER);
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    state.init(&mut rng);
+    let mut now = local_search_with_observer(state, local_search_number, &mut rng, statistics, &mut NoObserver);
+    let mut best = now;
+    for iteration in 0..iteration_number {
+        let is_improved = iterated_local_search_step(&mut now, &mut best, local_search_number, kick_number, acceptance, &mut rng, statistics);
+        if is_improved && observer.on_event(&SearchEvent::NewBest { iteration, score: best.1 as f64 }) == SearchControl::Abort {
+            break;
+        }
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: now.1 as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    best.0
+}
+#[allow(unused)]
+fn iterated_local_search_with_time_threshold(state: GameState, local_search_number: usize, kick_number: usize, restart_number: usize, acceptance: Acceptance, time_threshold: f64, seed: u64) -> GameState {
+    iterated_local_search_with_time_threshold_and_observer(state, local_search_number, kick_number, restart_number, acceptance, time_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn iterated_local_search_with_time_threshold_and_observer(state: GameState, local_search_number: usize, kick_number: usize, restart_number: usize, acceptance: Acceptance, time_threshold: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    // time_threshold を restart_number 回の試行に等分し、それぞれ random な初期状態から反復局所探索を行う。
+    // 遷移の回数が前もって分からないので、受理率は呼び出し側が with_acceptance_window で区間を決めたときだけ記録する。
+    // observer には iterated_local_search と同じ event を、全ての試行を通した kick の番号で送る。NewBest は全体の最良スコアが更新されたときに送る。
+    let start_time = std::time::Instant::now();
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let restart_number = restart_number.max(1);
+    // best のスコアは -INF から始めるので、最初の試行の結果で必ず置き換わる。
+    let mut best = (state, -INF);
+    let mut iteration = 0;
+    let mut is_aborted = false;
+    for _ in 0..restart_number {
+        let time_keeper = TimeKeeper::build(time_threshold / restart_number as f64);
+        let mut now = local_search_with_observer(random(state, rng.gen::<u64>()), local_search_number, &mut rng, statistics, &mut NoObserver);
+        let mut restart_best = now;
+        while !is_aborted && !time_keeper.is_time_over() {
+            iterated_local_search_step(&mut now, &mut restart_best, local_search_number, kick_number, acceptance, &mut rng, statistics);
+            let is_improved = restart_best.1 > best.1;
+            if is_improved {
+                best = restart_best;
+            }
+            is_aborted = (is_improved && observer.on_event(&SearchEvent::NewBest { iteration, score: restart_best.1 as f64 }) == SearchControl::Abort)
+                || observer.on_event(&SearchEvent::Iteration { iteration, score: now.1 as f64 }) == SearchControl::Abort;
+            iteration += 1;
+        }
+        if restart_best.1 > best.1 {
+            best = restart_best;
+        }
+        if is_aborted {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    best.0
+}
+#[allow(unused)]
+struct Replica {
+    // parallel_tempering で用いる、一つの温度に対応する鎖。
+    state: GameState,
+    score: isize,
+    best_state: GameState,
+    best_score: isize,
+    rng: Xorshift,
+}
+#[allow(unused)]
+impl Replica {
+    fn new(mut state: GameState, seed: u64) -> Self {
+        let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+        state.init(&mut rng);
+        let score = state.get_score();
+        Self {
+            state,
+            score,
+            best_state: state,
+            best_score: score,
+            rng,
+        }
+    }
+
+    fn anneal(&mut self, number: usize, temp: f64) {
+        // 温度 temp を固定して number 回だけ遷移を試す。
+        for _ in 0..number {
+            let mut now_state = self.state;
+            let now_score = match now_state.transition_with_delta(&mut self.rng) {
+                Some(score) => score,
+                None => now_state.get_score(),
+            };
+            let probability = ((now_score - self.score) as f64 / temp).exp();
+            if now_score > self.score || probability > self.rng.next_f64() {
+                self.score = now_score;
+                self.state = now_state;
+                if self.score > self.best_score {
+                    self.best_score = self.score;
+                    self.best_state = self.state;
+                }
+            }
+        }
+    }
+}
+#[allow(unused)]
+fn swap_replicas(replicas: &mut [Replica], temps: &[f64], parity: usize, rng: &mut Xorshift, statistics: &mut SearchStatistics) {
+    // parity が 0 なら (0, 1), (2, 3), ...、1 なら (1, 2), (3, 4), ... の組で、隣り合う温度の鎖の状態の交換を試みる。
+    // 交換するのは状態とスコアだけで、各鎖の温度、最良状態、乱数はそのまま残す。
+    for k in (parity..temps.len().saturating_sub(1)).step_by(2) {
+        let delta = (replicas[k + 1].score - replicas[k].score) as f64 * (1.0 / temps[k] - 1.0 / temps[k + 1]);
+        let is_accepted = delta >= 0.0 || delta.exp() > rng.next_f64();
+        statistics.record_acceptance(is_accepted);
+        if is_accepted {
+            let (low, high) = replicas.split_at_mut(k + 1);
+            std::mem::swap(&mut low[k].state, &mut high[0].state);
+            std::mem::swap(&mut low[k].score, &mut high[0].score);
+        }
+    }
+}
+#[allow(unused)]
+fn parallel_tempering(state: GameState, number: usize, temps: &[f64], swap_interval: usize, is_multi_thread: bool, seed: u64) -> GameState {
+    parallel_tempering_with_observer(state, number, temps, swap_interval, is_multi_thread, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn parallel_tempering_with_observer(state: GameState, number: usize, temps: &[f64], swap_interval: usize, is_multi_thread: bool, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    // temps[k] の温度で鎖を一つずつ走らせ、swap_interval 回ごとに隣り合う温度の鎖で状態の交換を試みる。
+    // is_multi_thread が true のときは、交換と交換の間を鎖ごとに別スレッドで走らせる。
+    // 受理率は鎖の中の遷移ではなく、鎖の間の交換について記録する。
+    // 交換を試みるたびに observer に全ての鎖を通した最高スコアを Iteration として送り、それが更新されたら NewBest を送る。
+    // Abort が返されたらそこで打ち切る。
+    let start_time = std::time::Instant::now();
+    let swap_interval = swap_interval.max(1);
+    statistics.set_acceptance_window_if_unset(number / swap_interval * (temps.len() / 2) / ACCEPTANCE_RATE_POINT_NUMBER);
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let mut replicas = (0..temps.len()).map(|_| Replica::new(state, rng.gen::<u64>())).collect::<Vec<_>>();
+    let mut max_score = replicas.iter().map(|replica| replica.best_score).max().unwrap_or(-INF);
+    let mut i = 0;
+    while i < number {
+        let step = swap_interval.min(number - i);
+        if is_multi_thread {
+            std::thread::scope(|scope| {
+                for (replica, &temp) in replicas.iter_mut().zip(temps) {
+                    scope.spawn(move || replica.anneal(step, temp));
+                }
+            });
+        } else {
+            for (replica, &temp) in replicas.iter_mut().zip(temps) {
+                replica.anneal(step, temp);
+            }
+        }
+        statistics.generated_state_number += step * replicas.len();
+        swap_replicas(&mut replicas, temps, i / swap_interval % 2, &mut rng, statistics);
+        i += step;
+        let score = replicas.iter().map(|replica| replica.best_score).max().unwrap_or(-INF);
+        if score > max_score {
+            max_score = score;
+            if observer.on_event(&SearchEvent::NewBest { iteration: i, score: max_score as f64 }) == SearchControl::Abort {
+                break;
+            }
+        }
+        if observer.on_event(&SearchEvent::Iteration { iteration: i, score: max_score as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    match replicas.into_iter().max_by_key(|replica| replica.best_score) {
+        Some(replica) => replica.best_state,
+        None => random(state, rng.gen::<u64>()),
+    }
+}
+
+#[allow(unused)]
+fn tournament_selection(population: &[(isize, GameState)], tournament_size: usize, rng: &mut Xorshift) -> GameState {
+    // population から tournament_size 個を無作為に選び、その中で最もスコアの高いものを返す。
+    let mut best = population[rng.next_index(population.len())];
+    for _ in 1..tournament_size {
+        let candidate = population[rng.next_index(population.len())];
+        if candidate.0 > best.0 {
+            best = candidate;
+        }
+    }
+    best.1
+}
+#[allow(unused)]
+fn next_generation(population: &[(isize, GameState)], elite_number: usize, tournament_size: usize, mutation_rate: f64, rng: &mut Xorshift) -> Vec<(isize, GameState)> {
+    // スコアの高い順に並んだ population から、上位 elite_number 個をそのまま残し、残りを交叉と突然変異で作る。
+    let mut next_population = population.iter().take(elite_number).copied().collect::<Vec<_>>();
+    while next_population.len() < population.len() {
+        let first_parent = tournament_selection(population, tournament_size, rng);
+        let second_parent = tournament_selection(population, tournament_size, rng);
+        let mut child = first_parent.crossover(&second_parent, rng);
+        if rng.next_f64() < mutation_rate {
+            child.transition(rng);
+        }
+        next_population.push((child.get_score(), child));
+    }
+    next_population.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    next_population
+}
+#[allow(unused)]
+fn initial_population(state: GameState, population_size: usize, rng: &mut Xorshift) -> Vec<(isize, GameState)> {
+    let mut population = (0..population_size.max(1))
+        .map(|_| {
+            let mut now_state = random(state, rng.gen::<u64>());
+            (now_state.get_score(), now_state)
+        })
+        .collect::<Vec<_>>();
+    population.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+    population
+}
+#[allow(unused)]
+fn notify_generation(population: &[(isize, GameState)], iteration: usize, max_score: &mut isize, observer: &mut dyn SearchObserver) -> SearchControl {
+    // スコアの高い順に並んだ population の先頭が max_score を超えたら NewBest を、続けて Iteration を observer に送る。
+    if population[0].0 > *max_score {
+        *max_score = population[0].0;
+        if observer.on_event(&SearchEvent::NewBest { iteration, score: *max_score as f64 }) == SearchControl::Abort {
+            return SearchControl::Abort;
+        }
+    }
+    observer.on_event(&SearchEvent::Iteration { iteration, score: population[0].0 as f64 })
+}
+#[allow(unused)]
+fn genetic_algorithm(state: GameState, population_size: usize, generation_number: usize, elite_number: usize, tournament_size: usize, mutation_rate: f64, seed: u64) -> GameState {
+    genetic_algorithm_with_observer(state, population_size, generation_number, elite_number, tournament_size, mutation_rate, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn genetic_algorithm_with_observer(state: GameState, population_size: usize, generation_number: usize, elite_number: usize, tournament_size: usize, mutation_rate: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    // 統計の層の数は作り終えた世代の数になる。
+    // 世代を一つ作るたびに observer に先頭の個体のスコアを Iteration として送り、最高スコアが更新されたら NewBest を送る。
+    // Abort が返されたらそこで打ち切る。
+    let start_time = std::time::Instant::now();
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let mut population = initial_population(state, population_size, &mut rng);
+    statistics.generated_state_number += population.len();
+    let mut max_score = population[0].0;
+    for iteration in 0..generation_number {
+        population = next_generation(&population, elite_number, tournament_size, mutation_rate, &mut rng);
+        statistics.generated_state_number += population.len().saturating_sub(elite_number);
+        statistics.completed_layer_number += 1;
+        if notify_generation(&population, iteration, &mut max_score, observer) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    population[0].1
+}
+#[allow(unused)]
+fn genetic_algorithm_with_time_threshold(state: GameState, population_size: usize, elite_number: usize, tournament_size: usize, mutation_rate: f64, time_threshold: f64, seed: u64) -> GameState {
+    genetic_algorithm_with_time_threshold_and_observer(state, population_size, elite_number, tournament_size, mutation_rate, time_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn genetic_algorithm_with_time_threshold_and_observer(state: GameState, population_size: usize, elite_number: usize, tournament_size: usize, mutation_rate: f64, time_threshold: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> GameState {
+    let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
+    let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+    let mut population = initial_population(state, population_size, &mut rng);
+    statistics.generated_state_number += population.len();
+    let mut max_score = population[0].0;
+    for iteration in 0.. {
+        if time_keeper.is_time_over() {
+            break;
+        }
+        population = next_generation(&population, elite_number, tournament_size, mutation_rate, &mut rng);
+        statistics.generated_state_number += population.len().saturating_sub(elite_number);
+        statistics.completed_layer_number += 1;
+        if notify_generation(&population, iteration, &mut max_score, observer) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    population[0].1
+}
 
 #[allow(unused)]
 pub fn play_game(seed: u64) -> isize {
-    let _state = GameState::new(seed);
-    let mut state = hill_climb(_state, 5);
+    play_game_with_hill_climb(seed, 5)
+}
+#[allow(unused)]
+pub fn play_game_with_hill_climb(seed: u64, number: usize) -> isize {
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let state = GameState::new(rng.gen::<u64>());
+    let mut state = hill_climb(state, number, rng.gen::<u64>());
+    state.get_score()
+}
+#[allow(unused)]
+pub fn play_game_with_simulated_annealing(seed: u64, number: usize, start_temp: f64, end_temp: f64) -> isize {
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let state = GameState::new(rng.gen::<u64>());
+    let mut state = simulated_annealing(state, number, start_temp, end_temp, rng.gen::<u64>());
     state.get_score()
 }
 #[allow(unused)]
-pub fn test_ai_score(game_number: usize) -> f64 {
-    let mut rng = rand::thread_rng();
-    let mut score_sum = 0;
-    for _ in 0..game_number {
-        score_sum += play_game(rng.gen::<u64>());
+pub fn play_game_with_time_threshold(seed: u64, time_threshold: f64, local_search_number: usize, kick_number: usize, acceptance: Acceptance) -> isize {
+    // 盤面を作るのにかかった時間も time_threshold に含め、探索には残りの時間を渡す。
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let state = GameState::new(rng.gen::<u64>());
+    let search_time_threshold = time_threshold - start_time.elapsed().as_secs_f64();
+    let mut state = iterated_local_search_with_time_threshold(state, local_search_number, kick_number, 1, acceptance, search_time_threshold, rng.gen::<u64>());
+    state.get_score()
+}
+#[allow(unused)]
+pub fn test_ai_score(game_number: usize, seed: u64) -> ScoreSummary {
+    test_ai_score_with(&play_game, game_number, seed)
+}
+#[allow(unused)]
+pub fn test_ai_score_with(play_game: &dyn Fn(u64) -> isize, game_number: usize, seed: u64) -> ScoreSummary {
+    // play_game を game_seeds で seed から作ったシードの列で動かす。
+    ScoreSummary::build(game_seeds(game_number, seed).into_iter().map(|seed| (seed, play_game(seed))).collect())
+}
+#[allow(unused)]
+pub fn test_ai_score_in_parallel(game_number: usize, seed: u64, thread_number: usize, time_threshold: f64) -> EvaluationResult {
+    evaluate(&game_seeds(game_number, seed), thread_number, time_threshold, &|seed, time_threshold| play_game_with_time_threshold(seed, time_threshold, 5, 1, Acceptance::BetterOrEqual))
+}
+#[allow(unused)]
+pub fn compare_ai_score(play_game: &dyn Fn(u64) -> isize, other_play_game: &dyn Fn(u64) -> isize, game_number: usize, seed: u64) -> PairedComparison {
+    // 二つの play_game を同じシードの列で動かし、スコアの差を対応のある比較で評価する。
+    PairedComparison::build(game_seeds(game_number, seed).into_iter().map(|seed| (seed, play_game(seed), other_play_game(seed))).collect())
+}
+#[allow(unused)]
+pub fn tune_simulated_annealing(number: usize, method: TuningMethod, game_number: usize, seed: u64) -> Result<TuningResult, String> {
+    // number 回の遷移を行う simulated_annealing の start_temp と end_temp を、test_ai_score の平均スコアが高くなるように選ぶ。
+    let parameters = [
+        Parameter::build("start_temp", ParameterRange::Float { min: 1.0, max: 1000.0, is_log_scale: true })?,
+        Parameter::build("end_temp", ParameterRange::Float { min: 0.01, max: 10.0, is_log_scale: true })?,
+    ];
+    let objective = |configuration: &[f64], game_number: usize| {
+        let (start_temp, end_temp) = (configuration[0], configuration[1]);
+        test_ai_score_with(&|seed| play_game_with_simulated_annealing(seed, number, start_temp, end_temp), game_number, seed).mean
+    };
+    tune(&parameters, &objective, method, game_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delta_evaluation_matches_full_evaluation() {
+        // transition_with_delta と transition_with_neighborhood_and_delta の返すスコアが、キャッシュを捨てて get_score で計算し直したスコアと一致することを確かめる。
+        // 差分計算を続けたときにキャッシュがずれないよう、遷移を受理した状態から次の遷移を行う。
+        let mut checked_number = 0;
+        for seed in 0..10 {
+            let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+            let mut state = random(GameState::new(rng.gen::<u64>()), rng.gen::<u64>());
+            state.get_score();
+            for i in 0..200 {
+                let mut now_state = state;
+                // 近傍を選ばない遷移と、adaptive_simulated_annealing で使う各近傍の遷移を順に確かめる。
+                let score = match NEIGHBORHOODS.get(i % (NEIGHBORHOODS.len() + 1)) {
+                    Some(&neighborhood) => now_state.transition_with_neighborhood_and_delta(neighborhood, &mut rng),
+                    None => now_state.transition_with_delta(&mut rng),
+                };
+                if let Some(score) = score {
+                    let mut full_state = now_state;
+                    full_state.reset();
+                    assert_eq!(full_state.get_score(), score);
+                    checked_number += 1;
+                    state = now_state;
+                }
+            }
+        }
+        assert!(checked_number > 0);
+    }
+
+    #[test]
+    fn timed_search_stays_within_the_time_limit() {
+        // 盤面を作る時間も含めて、evaluate が測る一ゲームの時間が time_threshold を超えない。
+        let result = test_ai_score_in_parallel(4, 0, 1, 0.2);
+        assert_eq!(result.time_over_number, 0, "{:?}", result.elapsed_times);
+    }
+
+    #[test]
+    fn kick_and_local_search_never_lose_the_best() {
+        // 出発点の局所最適を best としたとき、kick して局所探索した後の best は、どの受理規則でも出発点より悪くならない。
+        for acceptance in [Acceptance::Better, Acceptance::BetterOrEqual, Acceptance::Always, Acceptance::Threshold(10)] {
+            for seed in 0..5 {
+                let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+                let state = random(GameState::new(rng.gen::<u64>()), rng.gen::<u64>());
+                let start = local_search_with_observer(state, 20, &mut rng, &mut SearchStatistics::default(), &mut NoObserver);
+                let (mut now, mut best) = (start, start);
+                for kick_number in [1, 1, 3, 3, 5] {
+                    let best_score = best.1;
+                    let is_improved = iterated_local_search_step(&mut now, &mut best, 20, kick_number, acceptance, &mut rng, &mut SearchStatistics::default());
+                    assert!(best.1 >= best_score && best.1 >= start.1);
+                    assert_eq!(is_improved, best.1 > best_score);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn replica_swap_keeps_the_states() {
+        // 交換は鎖の間で状態とスコアの組を入れ替えるだけで、その多重集合は変わらない。
+        let state_key = |replica: &Replica| (replica.score, replica.state.player.pos);
+        let temps = [1.0, 3.0, 10.0, 30.0, 100.0];
+        let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(0);
+        let mut replicas = temps.iter().map(|_| Replica::new(GameState::new(0), rng.gen::<u64>())).collect::<Vec<_>>();
+        let mut states = replicas.iter().map(state_key).collect::<Vec<_>>();
+        states.sort();
+        let mut swapped_number = 0;
+        for i in 0..100 {
+            let before = replicas.iter().map(state_key).collect::<Vec<_>>();
+            swap_replicas(&mut replicas, &temps, i % 2, &mut rng, &mut SearchStatistics::default());
+            let after = replicas.iter().map(state_key).collect::<Vec<_>>();
+            swapped_number += before.iter().zip(after.iter()).filter(|(before, after)| before != after).count();
+            let mut now_states = after;
+            now_states.sort();
+            assert_eq!(now_states, states);
+        }
+        assert!(swapped_number > 0);
+    }
+
+    #[test]
+    fn elitism_never_lowers_the_best_score() {
+        // elite_number が 1 以上なら、突然変異の多い世代でも先頭の個体のスコアは下がらない。
+        for seed in 0..3 {
+            let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(seed);
+            let mut population = initial_population(GameState::new(seed), 20, &mut rng);
+            for _ in 0..30 {
+                let best_score = population[0].0;
+                population = next_generation(&population, 1, 3, 1.0, &mut rng);
+                assert_eq!(population.len(), 20);
+                assert!(population[0].0 >= best_score);
+                assert!(population.windows(2).all(|pair| pair[0].0 >= pair[1].0));
+            }
+        }
+    }
+
+    #[test]
+    fn neighborhood_weights_stay_positive_and_normalised() {
+        // どんな成否の列の後でも重みは [MIN_NEIGHBORHOOD_WEIGHT, 1] にあり、
+        // select_neighborhood が各近傍を選ぶ頻度は重みを和で割った確率に近く、その確率の和は 1 になる。
+        let mut rng: Xorshift = rand::SeedableRng::seed_from_u64(0);
+        let mut statistics = NEIGHBORHOODS.iter().map(|&neighborhood| NeighborhoodStatistics::new(neighborhood)).collect::<Vec<_>>();
+        let success_rates = [0.0, 0.3, 1.0];
+        for _ in 0..20 {
+            for _ in 0..50 {
+                for (statistic, &success_rate) in statistics.iter_mut().zip(success_rates.iter()) {
+                    let is_accepted = rng.next_f64() < success_rate;
+                    statistic.record(is_accepted, is_accepted);
+                }
+            }
+            let total_weight = statistics.iter().map(|statistic| statistic.weight()).sum::<f64>();
+            let probabilities = statistics.iter().map(|statistic| statistic.weight() / total_weight).collect::<Vec<_>>();
+            assert!(statistics.iter().all(|statistic| (MIN_NEIGHBORHOOD_WEIGHT..=1.0).contains(&statistic.weight())));
+            assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            let sample_number = 20000;
+            let mut counts = [0; 3];
+            for _ in 0..sample_number {
+                counts[select_neighborhood(&statistics, &mut rng)] += 1;
+            }
+            for (&count, &probability) in counts.iter().zip(probabilities.iter()) {
+                assert!((count as f64 / sample_number as f64 - probability).abs() < 0.02, "{:?} {:?}", counts, probabilities);
+            }
+        }
+        assert_eq!(statistics[0].weight(), MIN_NEIGHBORHOOD_WEIGHT);
+    }
+
+    #[test]
+    fn temperature_achieves_the_target_acceptance() {
+        // 悪化量 1, 2, ..., 100 の遷移の受理確率の平均が、求めた温度で目標の受理率になる。目標が高いほど温度も高い。
+        let deltas = (1..=100).map(|delta| delta as f64).collect::<Vec<_>>();
+        let average_acceptance = |temp: f64| deltas.iter().map(|delta| (-delta / temp).exp()).sum::<f64>() / deltas.len() as f64;
+        let mut last_temp = 0.0;
+        for acceptance in [0.01, 0.1, 0.5, 0.9, 0.99] {
+            let temp = temperature_for_acceptance(&deltas, acceptance);
+            assert!((average_acceptance(temp) - acceptance).abs() < 1e-6, "{} {}", acceptance, temp);
+            assert!(temp > last_temp);
+            last_temp = temp;
+        }
+        // 悪化量が一つなら、exp(-delta / temp) = acceptance を解いた温度になる。
+        assert!((temperature_for_acceptance(&[10.0], 0.5) - 10.0 / 2f64.ln()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn abort_on_new_best_stops_hill_climb() {
+        // 最初の NewBest で Abort を返すと、その遷移の後は一つも遷移を試さず、その時点の状態を返す。
+        let mut events = Vec::new();
+        let mut observer = |event: &SearchEvent| {
+            events.push(*event);
+            match event {
+                SearchEvent::NewBest { .. } => SearchControl::Abort,
+                _ => SearchControl::Continue,
+            }
+        };
+        let mut statistics = SearchStatistics::default();
+        let mut state = hill_climb_with_observer(GameState::new(0), 1000, 0, &mut statistics, &mut observer);
+        let Some(&SearchEvent::NewBest { iteration, score }) = events.last() else {
+            panic!("{:?}", events);
+        };
+        assert_eq!(events.len(), iteration + 1);
+        assert!(events[..iteration].iter().enumerate().all(|(i, event)| matches!(*event, SearchEvent::Iteration { iteration, .. } if iteration == i)));
+        assert_eq!(statistics.generated_state_number, iteration + 1);
+        assert_eq!(state.get_score() as f64, score);
+    }
+
+    #[test]
+    fn acceptance_parses_command_line_names() {
+        assert_eq!("better".parse(), Ok(Acceptance::Better));
+        assert_eq!("better-or-equal".parse(), Ok(Acceptance::BetterOrEqual));
+        assert_eq!("always".parse(), Ok(Acceptance::Always));
+        assert_eq!("threshold:3".parse(), Ok(Acceptance::Threshold(3)));
+        assert!("threshold:x".parse::<Acceptance>().is_err());
+        assert!("worse".parse::<Acceptance>().is_err());
     }
-    score_sum as f64 / game_number as f64
 }
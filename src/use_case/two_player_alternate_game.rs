@@ -0,0 +1,1089 @@
+use rand::*;
+
+use crate::search_observer::{NoObserver, SearchControl, SearchEvent, SearchObserver};
+use crate::search_statistics::SearchStatistics;
+use crate::statistics::{self, ArenaResult};
+use crate::time_keeper::TimeKeeper;
+use crate::transposition_table::TranspositionTable;
+
+pub const H: usize = 5;
+pub const W: usize = 5;
+
+#[allow(unused)]
+#[derive(Clone, Copy)]
+pub struct Player {
+    pub x: usize,
+    pub y: usize,
+    pub game_score: isize,
+}
+#[allow(unused)]
+impl Player {
+    pub fn new(x: usize, y: usize) -> Self {
+        // (x, y) に置かれた、得点 0 の player を返す関数
+        Self {
+            x,
+            y,
+            game_score: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+#[allow(unused)]
+pub struct Board {
+    pub points: [[isize; W]; H],
+}
+#[allow(unused)]
+impl Board {
+    pub fn new(seed: u64) -> Self {
+        let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+        // 初期化された盤面を返す関数。
+        let mut points = [[0; W]; H];
+        for points in points.iter_mut() {
+            for point in points.iter_mut() {
+                *point = (rng.gen::<usize>() % 10) as isize;
+            }
+        }
+        Self {
+            points,
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    D(usize, usize),
+}
+#[allow(unused)]
+const ACTIONS: [Action; 4] = [Action::D(1, 0), Action::D(!0, 0), Action::D(0, 1), Action::D(0, !0)];
+#[allow(unused)]
+pub const INF: isize = isize::MAX / 2;
+
+const fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+const fn zobrist_keys(salt: u64) -> [[u64; W]; H] {
+    let mut keys = [[0; W]; H];
+    let mut x = 0;
+    while x < H {
+        let mut y = 0;
+        while y < W {
+            keys[x][y] = splitmix64(salt.wrapping_mul(0x1000_0000_01B3) ^ (x * W + y) as u64);
+            y += 1;
+        }
+        x += 1;
+    }
+    keys
+}
+// Zobrist hash に用いる乱数。手番の player がいるマス、相手がいるマス、得点が 0 のマスそれぞれに割り当てる。
+const ZOBRIST_PLAYER_KEYS: [[[u64; W]; H]; 2] = [zobrist_keys(1), zobrist_keys(2)];
+const ZOBRIST_ZERO_KEYS: [[u64; W]; H] = zobrist_keys(3);
+
+#[allow(unused)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WinningStatus {
+    // 手番の player から見た勝敗。game が終了していなければ None。
+    Win,
+    Lose,
+    Draw,
+    None,
+}
+
+#[derive(Clone, Copy)]
+#[allow(unused)]
+pub struct GameState {
+    pub board: Board,
+    pub turn: usize,
+    // players[0] が手番の player を表す。
+    pub players: [Player; 2],
+    pub end_turn: usize,
+    // 盤面と player の位置についての Zobrist hash。advance で差分更新する。
+    zobrist_hash: u64,
+}
+#[allow(unused)]
+impl GameState {
+    pub fn new(seed: u64) -> Self {
+        // GameState をシード値を用いて作成する。
+        let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+        let end_turn = 10;
+        let mut board = Board::new(rng.gen::<u64>());
+        let players = [Player::new(H / 2, W / 2 - 1), Player::new(H / 2, W / 2 + 1)];
+        for player in players.iter() {
+            board.points[player.x][player.y] = 0;
+        }
+        Self::build(board, players, end_turn)
+    }
+    pub fn build(board: Board, players: [Player; 2], end_turn: usize) -> Self {
+        // GameState を情報が与えられた前提で作成する。
+        let mut state = Self {
+            board,
+            turn: 0,
+            players,
+            end_turn,
+            zobrist_hash: 0,
+        };
+        state.update_zobrist_hash();
+        state
+    }
+
+    pub fn update_zobrist_hash(&mut self) {
+        // zobrist_hash を一から計算し直す。board や players を直接書き換えた後に呼ぶ。
+        self.zobrist_hash = 0;
+        for (points, keys) in self.board.points.iter().zip(ZOBRIST_ZERO_KEYS.iter()) {
+            for (&point, &key) in points.iter().zip(keys.iter()) {
+                if point == 0 {
+                    self.zobrist_hash ^= key;
+                }
+            }
+        }
+        for (player_id, player) in self.players.iter().enumerate() {
+            self.zobrist_hash ^= ZOBRIST_PLAYER_KEYS[player_id][player.x][player.y];
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        // game が終了しているかどうかを返す。
+        self.turn == self.end_turn
+    }
+
+    pub fn advance(&mut self, action: Action) {
+        let Action::D(dx, dy) = action;
+        for (player_id, player) in self.players.iter().enumerate() {
+            self.zobrist_hash ^= ZOBRIST_PLAYER_KEYS[player_id][player.x][player.y];
+        }
+        let player = &mut self.players[0];
+        player.x = player.x.wrapping_add(dx);
+        player.y = player.y.wrapping_add(dy);
+        assert!(player.x < H && player.y < W);
+        if self.board.points[player.x][player.y] != 0 {
+            player.game_score += self.board.points[player.x][player.y];
+            self.board.points[player.x][player.y] = 0;
+            self.zobrist_hash ^= ZOBRIST_ZERO_KEYS[player.x][player.y];
+        }
+        self.players.swap(0, 1);
+        for (player_id, player) in self.players.iter().enumerate() {
+            self.zobrist_hash ^= ZOBRIST_PLAYER_KEYS[player_id][player.x][player.y];
+        }
+        self.turn += 1;
+    }
+
+    pub fn legal_actions(&self) -> Vec<Action> {
+        // ACTIONS 全体の中で手番の player に許容される action の全体を返す関数。
+        let player = &self.players[0];
+        ACTIONS.into_iter().filter(
+            |&Action::D(dx, dy)| (player.x.wrapping_add(dx) < H) && (player.y.wrapping_add(dy) < W)
+        ).collect::<Vec<Action>>()
+    }
+
+    pub fn hash(&self) -> u64 {
+        // 置換表の鍵に用いる局面の hash を返す。評価値は得点の差にもよるので、ターン数と得点の差も混ぜる。
+        let score = self.players[0].game_score - self.players[1].game_score;
+        self.zobrist_hash ^ splitmix64(((self.turn as u64) << 32) ^ score as u64)
+    }
+
+    fn get_score(&self) -> isize {
+        // 手番の player から見た盤面の評価値として、得点の差を返す。
+        self.players[0].game_score - self.players[1].game_score
+    }
+
+    pub fn get_winning_status(&self) -> WinningStatus {
+        // 手番の player から見た勝敗を返す。
+        if !self.is_done() {
+            return WinningStatus::None;
+        }
+        match self.get_score().cmp(&0) {
+            std::cmp::Ordering::Greater => WinningStatus::Win,
+            std::cmp::Ordering::Less => WinningStatus::Lose,
+            std::cmp::Ordering::Equal => WinningStatus::Draw,
+        }
+    }
+
+    pub fn is_first_player(&self) -> bool {
+        // 手番の player が先手かどうかを返す。
+        self.turn % 2 == 0
+    }
+
+    pub fn get_first_player_score(&self) -> isize {
+        // 先手から見た盤面の評価値を返す。
+        if self.is_first_player() {
+            self.get_score()
+        } else {
+            -self.get_score()
+        }
+    }
+
+    pub fn get_first_player_score_for_win_rate(&self) -> f64 {
+        // 先手から見た勝ち点を返す。勝ちなら 1、負けなら 0、それ以外は 0.5。
+        let score = match self.get_winning_status() {
+            WinningStatus::Win => 1.0,
+            WinningStatus::Lose => 0.0,
+            _ => 0.5,
+        };
+        if self.is_first_player() {
+            score
+        } else {
+            1.0 - score
+        }
+    }
+}
+
+// ここから先は、action を決定する方法を定める関数を作る。
+#[allow(unused)]
+pub fn random_action(state: &GameState, rng: &mut rand::rngs::StdRng) -> Option<Action> {
+    let legal_actions = state.legal_actions();
+    if legal_actions.is_empty() {
+        return None;
+    }
+    Some(legal_actions[rng.gen::<usize>() % legal_actions.len()])
+}
+#[allow(unused)]
+fn mini_max_score(state: &GameState, depth: usize) -> isize {
+    // 手番の player から見た、depth 手先まで読んだときの評価値を返す。
+    if state.is_done() || depth == 0 {
+        return state.get_score();
+    }
+    let legal_actions = state.legal_actions();
+    if legal_actions.is_empty() {
+        return state.get_score();
+    }
+    let mut best_score = -INF;
+    for &action in legal_actions.iter() {
+        let mut next_state = *state;
+        next_state.advance(action);
+        best_score = best_score.max(-mini_max_score(&next_state, depth - 1));
+    }
+    best_score
+}
+#[allow(unused)]
+fn mini_max_action(state: &GameState, depth: usize) -> Option<Action> {
+    let mut best_action = None;
+    let mut best_score = -INF;
+    for &action in state.legal_actions().iter() {
+        let mut next_state = *state;
+        next_state.advance(action);
+        let score = -mini_max_score(&next_state, depth.saturating_sub(1));
+        if best_action.is_none() || score > best_score {
+            best_action = Some(action);
+            best_score = score;
+        }
+    }
+    best_action
+}
+#[allow(unused)]
+pub fn alpha_beta_score(state: &GameState, alpha: isize, beta: isize, depth: usize) -> isize {
+    alpha_beta_score_with_statistics(state, alpha, beta, depth, &mut SearchStatistics::default())
+}
+#[allow(unused)]
+pub fn alpha_beta_score_with_statistics(state: &GameState, mut alpha: isize, beta: isize, depth: usize, statistics: &mut SearchStatistics) -> isize {
+    // mini_max_score と同じ値を、評価値が [alpha, beta] の外に出る枝を刈りながら求める。
+    if state.is_done() || depth == 0 {
+        return state.get_score();
+    }
+    let legal_actions = state.legal_actions();
+    if legal_actions.is_empty() {
+        return state.get_score();
+    }
+    statistics.expanded_node_number += 1;
+    for &action in legal_actions.iter() {
+        let mut next_state = *state;
+        next_state.advance(action);
+        statistics.generated_state_number += 1;
+        let score = -alpha_beta_score_with_statistics(&next_state, -beta, -alpha, depth - 1, statistics);
+        if score > alpha {
+            alpha = score;
+        }
+        if alpha >= beta {
+            return alpha;
+        }
+    }
+    alpha
+}
+#[allow(unused)]
+pub fn alpha_beta_action(state: &GameState, depth: usize) -> Option<Action> {
+    alpha_beta_action_with_observer(state, depth, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+pub fn alpha_beta_action_with_observer(state: &GameState, depth: usize, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // 根の最善手が更新されるたびに observer に NewBest を送り、Abort が返されたらそこまでの最善手を返す。
+    let start_time = std::time::Instant::now();
+    let mut best_action = None;
+    let mut alpha = -INF;
+    statistics.expanded_node_number += 1;
+    for (iteration, &action) in state.legal_actions().iter().enumerate() {
+        let mut next_state = *state;
+        next_state.advance(action);
+        statistics.generated_state_number += 1;
+        let score = -alpha_beta_score_with_statistics(&next_state, -INF, -alpha, depth.saturating_sub(1), statistics);
+        if best_action.is_none() || score > alpha {
+            best_action = Some(action);
+            alpha = score;
+            if observer.on_event(&SearchEvent::NewBest { iteration, score: score as f64 }) == SearchControl::Abort {
+                break;
+            }
+        }
+    }
+    statistics.record_depth(depth.min(state.end_turn - state.turn));
+    statistics.finish(start_time);
+    best_action
+}
+#[allow(unused)]
+fn alpha_beta_score_with_time_threshold(state: &GameState, mut alpha: isize, beta: isize, depth: usize, time_keeper: &TimeKeeper, statistics: &mut SearchStatistics) -> isize {
+    // alpha_beta_score と同じだが、時間切れになったら途中で 0 を返す。呼び出し側は結果を捨てること。
+    if time_keeper.is_time_over() {
+        return 0;
+    }
+    if state.is_done() || depth == 0 {
+        return state.get_score();
+    }
+    let legal_actions = state.legal_actions();
+    if legal_actions.is_empty() {
+        return state.get_score();
+    }
+    statistics.expanded_node_number += 1;
+    for &action in legal_actions.iter() {
+        let mut next_state = *state;
+        next_state.advance(action);
+        statistics.generated_state_number += 1;
+        let score = -alpha_beta_score_with_time_threshold(&next_state, -beta, -alpha, depth - 1, time_keeper, statistics);
+        if time_keeper.is_time_over() {
+            return 0;
+        }
+        if score > alpha {
+            alpha = score;
+        }
+        if alpha >= beta {
+            return alpha;
+        }
+    }
+    alpha
+}
+#[allow(unused)]
+fn alpha_beta_action_with_time_threshold(state: &GameState, depth: usize, time_keeper: &TimeKeeper, statistics: &mut SearchStatistics) -> Option<(Action, isize)> {
+    // depth 手先まで読み切れたときだけ最善手とその評価値を返し、時間切れになったら None を返す。
+    let mut best_action = None;
+    let mut alpha = -INF;
+    statistics.expanded_node_number += 1;
+    for &action in state.legal_actions().iter() {
+        let mut next_state = *state;
+        next_state.advance(action);
+        statistics.generated_state_number += 1;
+        let score = -alpha_beta_score_with_time_threshold(&next_state, -INF, -alpha, depth.saturating_sub(1), time_keeper, statistics);
+        if time_keeper.is_time_over() {
+            return None;
+        }
+        if best_action.is_none() || score > alpha {
+            best_action = Some(action);
+            alpha = score;
+        }
+    }
+    best_action.map(|action| (action, alpha))
+}
+#[allow(unused)]
+pub fn iterative_deepening_action(state: &GameState, time_threshold: f64) -> Option<Action> {
+    iterative_deepening_action_with_observer(state, time_threshold, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+pub fn iterative_deepening_action_with_observer(state: &GameState, time_threshold: f64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // 読む深さを一つずつ増やしながら alpha_beta 法を行い、時間内に読み切れた最も深い探索の最善手を返す。
+    // 深さ 1 の探索も終わらなかったときは、合法手の先頭を返す。統計の深さは読み切れた最も深い深さになる。
+    // 深さを一つ読み切るたびに observer に LayerCompleted を送り、Abort が返されたらその深さの最善手を返す。
+    let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
+    let mut best_action = state.legal_actions().first().copied();
+    for depth in 1.. {
+        match alpha_beta_action_with_time_threshold(state, depth, &time_keeper, statistics) {
+            Some((action, score)) => {
+                best_action = Some(action);
+                statistics.record_depth(depth);
+                if observer.on_event(&SearchEvent::LayerCompleted { depth, score: score as f64 }) == SearchControl::Abort {
+                    break;
+                }
+            }
+            None => break,
+        }
+        if depth >= state.end_turn - state.turn {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    best_action
+}
+
+#[allow(unused)]
+pub fn playout(state: &mut GameState, rng: &mut rand::rngs::StdRng) -> f64 {
+    // 両者が random_action を選び続けたときの、手番の player から見た勝ち点を返す。
+    match state.get_winning_status() {
+        WinningStatus::Win => 1.0,
+        WinningStatus::Lose => 0.0,
+        WinningStatus::Draw => 0.5,
+        WinningStatus::None => match random_action(state, rng) {
+            Some(action) => {
+                state.advance(action);
+                1.0 - playout(state, rng)
+            }
+            None => 0.5,
+        },
+    }
+}
+#[allow(unused)]
+struct Node {
+    // モンテカルロ木探索の節点。w は手番の player から見た勝ち点の和、n は訪問回数を表す。
+    state: GameState,
+    w: f64,
+    n: usize,
+    child_nodes: Vec<(Action, Node)>,
+}
+#[allow(unused)]
+impl Node {
+    fn new(state: GameState) -> Self {
+        Self {
+            state,
+            w: 0.0,
+            n: 0,
+            child_nodes: Vec::new(),
+        }
+    }
+
+    fn evaluate(&mut self, c: f64, expand_threshold: usize, depth: usize, rng: &mut rand::rngs::StdRng, statistics: &mut SearchStatistics) -> f64 {
+        // この節点から一回だけ探索し、手番の player から見た勝ち点を返す。
+        // 子を持たない節点は playout で評価し、訪問回数が expand_threshold に達したら展開する。
+        statistics.record_depth(depth);
+        let value = if self.state.is_done() {
+            playout(&mut self.state.clone(), rng)
+        } else if self.child_nodes.is_empty() {
+            let value = playout(&mut self.state.clone(), rng);
+            if self.n + 1 >= expand_threshold {
+                self.expand(statistics);
+            }
+            value
+        } else {
+            1.0 - self.next_child_node(c).evaluate(c, expand_threshold, depth + 1, rng, statistics)
+        };
+        self.w += value;
+        self.n += 1;
+        value
+    }
+
+    fn expand(&mut self, statistics: &mut SearchStatistics) {
+        self.child_nodes = self
+            .state
+            .legal_actions()
+            .into_iter()
+            .map(|action| {
+                let mut next_state = self.state;
+                next_state.advance(action);
+                (action, Node::new(next_state))
+            })
+            .collect();
+        statistics.expanded_node_number += 1;
+        statistics.generated_state_number += self.child_nodes.len();
+    }
+
+    fn next_child_node(&mut self, c: f64) -> &mut Node {
+        // 訪問していない子があればそれを、なければ UCB1 が最大の子を返す。
+        if let Some(k) = self.child_nodes.iter().position(|(_, child_node)| child_node.n == 0) {
+            return &mut self.child_nodes[k].1;
+        }
+        let t = self.child_nodes.iter().map(|(_, child_node)| child_node.n).sum::<usize>() as f64;
+        let ucb1 = |child_node: &Node| 1.0 - child_node.w / child_node.n as f64 + c * (2.0 * t.ln() / child_node.n as f64).sqrt();
+        let mut best_k = 0;
+        for k in 1..self.child_nodes.len() {
+            if ucb1(&self.child_nodes[k].1) > ucb1(&self.child_nodes[best_k].1) {
+                best_k = k;
+            }
+        }
+        &mut self.child_nodes[best_k].1
+    }
+
+    fn most_visited_action(&self) -> Option<Action> {
+        let mut best_action = None;
+        let mut best_n = 0;
+        for (action, child_node) in self.child_nodes.iter() {
+            if best_action.is_none() || child_node.n > best_n {
+                best_action = Some(*action);
+                best_n = child_node.n;
+            }
+        }
+        best_action
+    }
+}
+#[allow(unused)]
+pub fn mcts_action(state: &GameState, playout_number: usize, c: f64, expand_threshold: usize, seed: u64) -> Option<Action> {
+    mcts_action_with_observer(state, playout_number, c, expand_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+pub fn mcts_action_with_observer(state: &GameState, playout_number: usize, c: f64, expand_threshold: usize, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // c は UCB1 の探索の強さ、expand_threshold は節点を展開するまでの訪問回数を表す。
+    // 一回探索するたびに observer に根の平均の勝ち点を Iteration として送り、Abort が返されたらそこまでの統計で action を選ぶ。
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut root_node = Node::new(*state);
+    root_node.expand(statistics);
+    for iteration in 0..playout_number {
+        root_node.evaluate(c, expand_threshold, 0, &mut rng, statistics);
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: root_node.w / root_node.n as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    root_node.most_visited_action()
+}
+#[allow(unused)]
+pub fn mcts_action_with_time_threshold(state: &GameState, c: f64, expand_threshold: usize, time_threshold: f64, seed: u64) -> Option<Action> {
+    mcts_action_with_time_threshold_and_observer(state, c, expand_threshold, time_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+pub fn mcts_action_with_time_threshold_and_observer(state: &GameState, c: f64, expand_threshold: usize, time_threshold: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut root_node = Node::new(*state);
+    root_node.expand(statistics);
+    for iteration in 0.. {
+        if time_keeper.is_time_over() {
+            break;
+        }
+        root_node.evaluate(c, expand_threshold, 0, &mut rng, statistics);
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: root_node.w / root_node.n as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    root_node.most_visited_action()
+}
+
+#[allow(unused)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    // 置換表に登録した評価値が、真の値そのものか、下界か、上界かを表す。
+    Exact,
+    Lower,
+    Upper,
+}
+#[allow(unused)]
+#[derive(Clone, Copy)]
+pub struct AlphaBetaEntry {
+    score: isize,
+    bound: Bound,
+    best_action: Option<Action>,
+}
+#[allow(unused)]
+fn ordered_legal_actions(state: &GameState, best_action: Option<Action>) -> Vec<Action> {
+    // 置換表に登録された最善手があれば、それを先頭にした合法手を返す。
+    let mut legal_actions = state.legal_actions();
+    if let Some(k) = best_action.and_then(|best_action| legal_actions.iter().position(|&action| action == best_action)) {
+        legal_actions.swap(0, k);
+    }
+    legal_actions
+}
+#[allow(unused)]
+pub fn alpha_beta_score_with_transposition_table(state: &GameState, mut alpha: isize, mut beta: isize, depth: usize, time_keeper: &TimeKeeper, table: &mut TranspositionTable<AlphaBetaEntry>, statistics: &mut SearchStatistics) -> isize {
+    // alpha_beta_score_with_time_threshold と同じだが、置換表に登録された評価値の範囲で枝を刈り、最善手から順に調べる。
+    if time_keeper.is_time_over() {
+        return 0;
+    }
+    if state.is_done() || depth == 0 {
+        return state.get_score();
+    }
+    // 置換表の評価値で窓を狭める前の窓で、登録する評価値が上界か下界かを決める。
+    let (original_alpha, original_beta) = (alpha, beta);
+    let hash = state.hash();
+    let mut table_best_action = None;
+    if let Some((entry_depth, entry)) = table.get(hash) {
+        table_best_action = entry.best_action;
+        if entry_depth >= depth {
+            match entry.bound {
+                Bound::Exact => return entry.score,
+                Bound::Lower => alpha = alpha.max(entry.score),
+                Bound::Upper => beta = beta.min(entry.score),
+            }
+            if alpha >= beta {
+                return entry.score;
+            }
+        }
+    }
+    let legal_actions = ordered_legal_actions(state, table_best_action);
+    if legal_actions.is_empty() {
+        return state.get_score();
+    }
+    let mut best_score = -INF;
+    let mut best_action = None;
+    statistics.expanded_node_number += 1;
+    for &action in legal_actions.iter() {
+        let mut next_state = *state;
+        next_state.advance(action);
+        statistics.generated_state_number += 1;
+        let score = -alpha_beta_score_with_transposition_table(&next_state, -beta, -alpha, depth - 1, time_keeper, table, statistics);
+        if time_keeper.is_time_over() {
+            return 0;
+        }
+        if score > best_score {
+            best_score = score;
+            best_action = Some(action);
+        }
+        alpha = alpha.max(score);
+        if alpha >= beta {
+            break;
+        }
+    }
+    let bound = if best_score <= original_alpha {
+        Bound::Upper
+    } else if best_score >= original_beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    table.insert(hash, depth, AlphaBetaEntry { score: best_score, bound, best_action });
+    best_score
+}
+#[allow(unused)]
+pub fn alpha_beta_action_with_transposition_table(state: &GameState, depth: usize, time_keeper: &TimeKeeper, table: &mut TranspositionTable<AlphaBetaEntry>, statistics: &mut SearchStatistics) -> Option<(Action, isize)> {
+    // depth 手先まで読み切れたときだけ最善手とその評価値を返し、時間切れになったら None を返す。
+    let hash = state.hash();
+    let table_best_action = table.get(hash).and_then(|(_, entry)| entry.best_action);
+    let mut best_action = None;
+    let mut alpha = -INF;
+    statistics.expanded_node_number += 1;
+    for &action in ordered_legal_actions(state, table_best_action).iter() {
+        let mut next_state = *state;
+        next_state.advance(action);
+        statistics.generated_state_number += 1;
+        let score = -alpha_beta_score_with_transposition_table(&next_state, -INF, -alpha, depth.saturating_sub(1), time_keeper, table, statistics);
+        if time_keeper.is_time_over() {
+            return None;
+        }
+        if best_action.is_none() || score > alpha {
+            best_action = Some(action);
+            alpha = score;
+        }
+    }
+    table.insert(hash, depth, AlphaBetaEntry { score: alpha, bound: Bound::Exact, best_action });
+    best_action.map(|action| (action, alpha))
+}
+#[allow(unused)]
+pub fn iterative_deepening_action_with_transposition_table(state: &GameState, time_threshold: f64, table: &mut TranspositionTable<AlphaBetaEntry>) -> Option<Action> {
+    iterative_deepening_action_with_transposition_table_and_observer(state, time_threshold, table, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+pub fn iterative_deepening_action_with_transposition_table_and_observer(state: &GameState, time_threshold: f64, table: &mut TranspositionTable<AlphaBetaEntry>, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // iterative_deepening_action と同じだが、浅い探索の結果を置換表に残して深い探索の枝刈りと手の順番に用いる。
+    // table は呼び出し側が持つので、次の手番の探索でも使い回せる。
+    // 深さ 1 の探索も終わらなかったときは、合法手の先頭を返す。統計の深さは読み切れた最も深い深さになる。
+    // observer には iterative_deepening_action と同じ event を送る。
+    let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
+    let mut best_action = state.legal_actions().first().copied();
+    for depth in 1.. {
+        match alpha_beta_action_with_transposition_table(state, depth, &time_keeper, table, statistics) {
+            Some((action, score)) => {
+                best_action = Some(action);
+                statistics.record_depth(depth);
+                if observer.on_event(&SearchEvent::LayerCompleted { depth, score: score as f64 }) == SearchControl::Abort {
+                    break;
+                }
+            }
+            None => break,
+        }
+        if depth >= state.end_turn - state.turn {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    best_action
+}
+#[allow(unused)]
+#[derive(Clone, Copy, Default)]
+pub struct MctsEntry {
+    // 局面ごとのモンテカルロ木探索の統計。w は手番の player から見た勝ち点の和、n は訪問回数を表す。
+    w: f64,
+    n: usize,
+    // 展開した局面では、ACTIONS[k] で進んだ子の hash を child_hashes[k] に置く。合法でない action は None になる。
+    is_expanded: bool,
+    child_hashes: [Option<u64>; ACTIONS.len()],
+}
+#[allow(unused)]
+fn expand_with_transposition_table(state: &GameState, entry: &mut MctsEntry, statistics: &mut SearchStatistics) {
+    // 子の hash を一度だけ計算して entry に残す。置換表から消えた局面は、次に辿り着いたときにもう一度展開する。
+    for action in state.legal_actions() {
+        let k = ACTIONS.iter().position(|&legal_action| legal_action == action).unwrap();
+        let mut next_state = *state;
+        next_state.advance(action);
+        entry.child_hashes[k] = Some(next_state.hash());
+        statistics.generated_state_number += 1;
+    }
+    entry.is_expanded = true;
+    statistics.expanded_node_number += 1;
+}
+#[allow(unused)]
+fn next_child_action_with_transposition_table(entry: &MctsEntry, c: f64, table: &TranspositionTable<MctsEntry>) -> Option<Action> {
+    // 展開した entry の子の統計を置換表から読み、訪問していない子があればそれへの action を、なければ UCB1 が最大の子への action を返す。
+    let child_entries = ACTIONS
+        .into_iter()
+        .zip(entry.child_hashes)
+        .filter_map(|(action, hash)| Some((action, table.get(hash?).map(|(_, entry)| entry).unwrap_or_default())))
+        .collect::<Vec<_>>();
+    if let Some(&(action, _)) = child_entries.iter().find(|(_, entry)| entry.n == 0) {
+        return Some(action);
+    }
+    let t = child_entries.iter().map(|(_, entry)| entry.n).sum::<usize>() as f64;
+    let ucb1 = |entry: &MctsEntry| 1.0 - entry.w / entry.n as f64 + c * (2.0 * t.ln() / entry.n as f64).sqrt();
+    let mut best_action = None;
+    let mut best_ucb1 = 0.0;
+    for &(action, entry) in child_entries.iter() {
+        if best_action.is_none() || ucb1(&entry) > best_ucb1 {
+            best_action = Some(action);
+            best_ucb1 = ucb1(&entry);
+        }
+    }
+    best_action
+}
+#[allow(unused)]
+fn mcts_evaluate_with_transposition_table(state: &GameState, c: f64, expand_threshold: usize, depth: usize, table: &mut TranspositionTable<MctsEntry>, rng: &mut rand::rngs::StdRng, statistics: &mut SearchStatistics) -> f64 {
+    // 置換表に統計を置いて一回だけ探索し、手番の player から見た勝ち点を返す。depth は根からの深さを表す。
+    // 同じ局面は、木のどこから辿り着いても統計を共有する。訪問回数が expand_threshold に達するまでは playout で評価し、
+    // 達したときに一度だけ展開する。
+    statistics.record_depth(depth);
+    let hash = state.hash();
+    let mut entry = table.get(hash).map(|(_, entry)| entry).unwrap_or_default();
+    let next_state = if !state.is_done() && (depth == 0 || entry.n >= expand_threshold) {
+        if !entry.is_expanded {
+            expand_with_transposition_table(state, &mut entry, statistics);
+        }
+        next_child_action_with_transposition_table(&entry, c, table).map(|action| {
+            let mut next_state = *state;
+            next_state.advance(action);
+            next_state
+        })
+    } else {
+        None
+    };
+    let value = match next_state {
+        Some(next_state) => 1.0 - mcts_evaluate_with_transposition_table(&next_state, c, expand_threshold, depth + 1, table, rng, statistics),
+        None => playout(&mut state.clone(), rng),
+    };
+    entry.w += value;
+    entry.n += 1;
+    table.insert(hash, entry.n, entry);
+    value
+}
+#[allow(unused)]
+pub fn mcts_action_with_transposition_table(state: &GameState, playout_number: usize, c: f64, expand_threshold: usize, table: &mut TranspositionTable<MctsEntry>, seed: u64) -> Option<Action> {
+    mcts_action_with_transposition_table_and_observer(state, playout_number, c, expand_threshold, table, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+pub fn mcts_action_with_transposition_table_and_observer(state: &GameState, playout_number: usize, c: f64, expand_threshold: usize, table: &mut TranspositionTable<MctsEntry>, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // mcts_action と同じだが、節点を置換表に置くことで、手順が違うだけの同じ局面を一つの節点として扱う。
+    // 置換表の深さとして訪問回数を登録するので、ReplacementPolicy::DepthPreferred なら訪問回数が多い局面が残る。
+    // observer には mcts_action と同じ event を、根の統計を置換表から読んで送る。
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let hash = state.hash();
+    for iteration in 0..playout_number {
+        mcts_evaluate_with_transposition_table(state, c, expand_threshold, 0, table, &mut rng, statistics);
+        let score = table.get(hash).map_or(0.5, |(_, entry)| entry.w / entry.n as f64);
+        if observer.on_event(&SearchEvent::Iteration { iteration, score }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    let mut best_action = None;
+    let mut best_n = 0;
+    for action in state.legal_actions() {
+        let mut next_state = *state;
+        next_state.advance(action);
+        let n = table.get(next_state.hash()).map_or(0, |(_, entry)| entry.n);
+        if best_action.is_none() || n > best_n {
+            best_action = Some(action);
+            best_n = n;
+        }
+    }
+    best_action
+}
+
+#[allow(unused)]
+pub fn play_game(seed: u64) -> f64 {
+    // 先手が alpha_beta_action、後手が random_action で対戦し、先手から見た勝ち点を返す。
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut state = GameState::new(rng.gen::<u64>());
+    while !state.is_done() {
+        let action = if state.is_first_player() {
+            alpha_beta_action(&state, 4)
+        } else {
+            random_action(&state, &mut rng)
+        };
+        match action {
+            Some(action) => state.advance(action),
+            None => break,
+        }
+    }
+    state.get_first_player_score_for_win_rate()
+}
+#[allow(unused)]
+fn play_arena_game(seed: u64, first_ai: &dyn Fn(&GameState, u64) -> Option<Action>, second_ai: &dyn Fn(&GameState, u64) -> Option<Action>) -> GameState {
+    // first_ai を先手、second_ai を後手として一局対戦させ、終局した状態を返す。
+    // AI には手番ごとに seed から作ったシード値を渡すので、同じ seed の対局は再現できる。
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut state = GameState::new(rng.gen::<u64>());
+    while !state.is_done() {
+        let action = if state.is_first_player() { first_ai(&state, rng.gen::<u64>()) } else { second_ai(&state, rng.gen::<u64>()) };
+        match action {
+            Some(action) => state.advance(action),
+            None => break,
+        }
+    }
+    state
+}
+#[allow(unused)]
+pub fn arena(ai: &dyn Fn(&GameState, u64) -> Option<Action>, opponent_ai: &dyn Fn(&GameState, u64) -> Option<Action>, game_number: usize, seed: u64) -> ArenaResult {
+    // game_number 個のシードそれぞれで、ai と opponent_ai の先手と後手を入れ替えて二局ずつ対戦させる。
+    ArenaResult::play(game_number, seed, &|game_seed, is_first| {
+        let state = if is_first { play_arena_game(game_seed, ai, opponent_ai) } else { play_arena_game(game_seed, opponent_ai, ai) };
+        (state.get_first_player_score_for_win_rate(), state.get_first_player_score() as f64)
+    })
+}
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct LeagueEntry {
+    pub name: String,
+    pub rating: f64,
+    pub win: usize,
+    pub draw: usize,
+    pub lose: usize,
+}
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct LeagueResult {
+    // rating の高い順に並んだ、AI ごとの成績。
+    pub entries: Vec<LeagueEntry>,
+}
+impl std::fmt::Display for LeagueResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name_width = self.entries.iter().map(|entry| entry.name.len()).max().unwrap_or(0).max(4);
+        writeln!(f, "{:>4}  {:<name_width$}  {:>7}  {:>5}  {:>5}  {:>5}", "rank", "name", "rating", "win", "draw", "lose")?;
+        for (rank, entry) in self.entries.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>4}  {:<name_width$}  {:>7.1}  {:>5}  {:>5}  {:>5}",
+                rank + 1,
+                entry.name,
+                entry.rating,
+                entry.win,
+                entry.draw,
+                entry.lose
+            )?;
+        }
+        Ok(())
+    }
+}
+// league に渡す、名前つきの AI。
+#[allow(unused)]
+pub type NamedAi<'a> = (&'a str, &'a dyn Fn(&GameState, u64) -> Option<Action>);
+#[allow(unused)]
+pub fn league(ais: &[NamedAi], game_number: usize, seed: u64) -> LeagueResult {
+    // ais のすべての組について arena で game_number 個のシードずつ対戦させ、Bradley-Terry モデルで rating をつける。
+    let mut rngs: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let n = ais.len();
+    let mut points = vec![vec![0.0; n]; n];
+    let mut games = vec![vec![0.0; n]; n];
+    let mut entries = ais
+        .iter()
+        .map(|&(name, _)| LeagueEntry {
+            name: name.to_string(),
+            rating: 0.0,
+            win: 0,
+            draw: 0,
+            lose: 0,
+        })
+        .collect::<Vec<_>>();
+    for i in 0..n {
+        for j in i + 1..n {
+            let result = arena(ais[i].1, ais[j].1, game_number, rngs.gen::<u64>());
+            let game = (result.win + result.draw + result.lose) as f64;
+            points[i][j] = result.win as f64 + 0.5 * result.draw as f64;
+            points[j][i] = result.lose as f64 + 0.5 * result.draw as f64;
+            games[i][j] = game;
+            games[j][i] = game;
+            entries[i].win += result.win;
+            entries[i].draw += result.draw;
+            entries[i].lose += result.lose;
+            entries[j].win += result.lose;
+            entries[j].draw += result.draw;
+            entries[j].lose += result.win;
+        }
+    }
+    for (entry, rating) in entries.iter_mut().zip(statistics::bradley_terry_ratings(&points, &games)) {
+        entry.rating = rating;
+    }
+    entries.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+    LeagueResult { entries }
+}
+#[allow(unused)]
+pub fn test_first_player_win_rate(game_number: usize, seed: u64) -> f64 {
+    let mut rngs: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut score_sum = 0.0;
+    for _ in 0..game_number {
+        score_sum += play_game(rngs.gen::<u64>());
+    }
+    score_sum / game_number as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transposition_table::ReplacementPolicy;
+
+    const UP: Action = Action::D(!0, 0);
+    const DOWN: Action = Action::D(1, 0);
+    const LEFT: Action = Action::D(0, !0);
+    const RIGHT: Action = Action::D(0, 1);
+
+    fn random_position(seed: u64, turn: usize) -> GameState {
+        // GameState::new(seed) から両者が random_action を turn 手指した局面を返す。
+        let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+        let mut state = GameState::new(rng.gen::<u64>());
+        for _ in 0..turn {
+            state.advance(random_action(&state, &mut rng).unwrap());
+        }
+        state
+    }
+
+    #[test]
+    fn alpha_beta_matches_mini_max() {
+        for seed in 0..5 {
+            for turn in [0, 3, 7] {
+                let state = random_position(seed, turn);
+                for depth in 1..=4 {
+                    assert_eq!(alpha_beta_score(&state, -INF, INF, depth), mini_max_score(&state, depth));
+                    assert!(alpha_beta_action(&state, depth) == mini_max_action(&state, depth));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn iterative_deepening_matches_alpha_beta_at_its_deepest_depth() {
+        // 時間に余裕があれば終局まで読み切るので、その深さの alpha_beta_action と同じ手を返す。
+        for seed in 0..5 {
+            let state = random_position(seed, 5);
+            let mut statistics = SearchStatistics::default();
+            let action = iterative_deepening_action_with_observer(&state, 60.0, &mut statistics, &mut NoObserver);
+            assert_eq!(statistics.max_depth, state.end_turn - state.turn);
+            assert!(action == alpha_beta_action(&state, statistics.max_depth));
+        }
+    }
+
+    fn forced_win_position() -> GameState {
+        // 先手は右のマス (5 点) を取れば勝ち、それ以外の手では後手にそのマスを取られて負ける。
+        let mut board = Board { points: [[0; W]; H] };
+        board.points[2][2] = 5;
+        board.points[1][3] = 3;
+        GameState::build(board, [Player::new(2, 1), Player::new(2, 3)], 2)
+    }
+
+    #[test]
+    fn mcts_finds_the_forced_win() {
+        let state = forced_win_position();
+        assert!(alpha_beta_action(&state, 2) == Some(RIGHT));
+        for seed in 0..5 {
+            assert!(mcts_action(&state, 2000, 1.0, 10, seed) == Some(RIGHT));
+            let mut table = TranspositionTable::build(1 << 12, ReplacementPolicy::DepthPreferred);
+            assert!(mcts_action_with_transposition_table(&state, 2000, 1.0, 10, &mut table, seed) == Some(RIGHT));
+        }
+    }
+
+    #[test]
+    fn mcts_is_deterministic_for_a_seed() {
+        let state = random_position(0, 2);
+        let run = |seed: u64| {
+            let mut scores = Vec::new();
+            let mut observer = |event: &SearchEvent| {
+                if let SearchEvent::Iteration { score, .. } = *event {
+                    scores.push(score);
+                }
+                SearchControl::Continue
+            };
+            let action = mcts_action_with_observer(&state, 500, 1.0, 10, seed, &mut SearchStatistics::default(), &mut observer);
+            (action, scores)
+        };
+        for seed in 0..5 {
+            let (action, scores) = run(seed);
+            let (other_action, other_scores) = run(seed);
+            assert!(action == other_action);
+            assert_eq!(scores, other_scores);
+        }
+    }
+
+    #[test]
+    fn transposed_positions_have_the_same_hash() {
+        // 先手が同じ三マスを逆向きに回り、後手が同じ手を指すと、同じ局面になる。
+        for seed in 0..10 {
+            let state = GameState::new(seed);
+            let mut states = [state, state];
+            for (state, first_actions) in states.iter_mut().zip([[RIGHT, DOWN, LEFT, UP], [DOWN, RIGHT, UP, LEFT]]) {
+                for (first_action, second_action) in first_actions.into_iter().zip([UP, DOWN, UP, DOWN]) {
+                    state.advance(first_action);
+                    state.advance(second_action);
+                }
+            }
+            assert_eq!(states[0].hash(), states[1].hash());
+            let mut recomputed_state = states[0];
+            recomputed_state.update_zobrist_hash();
+            assert_eq!(recomputed_state.hash(), states[0].hash());
+            assert_ne!(state.hash(), states[0].hash());
+        }
+    }
+
+    #[test]
+    fn transposition_table_keeps_the_alpha_beta_score() {
+        // 時間制限がなければ、置換表を使っても、深さごとに新しく作っても使い回しても、alpha_beta_score と同じ評価値になる。
+        let time_keeper = TimeKeeper::build(f64::INFINITY);
+        for seed in 0..5 {
+            let state = GameState::new(seed);
+            let mut shared_table = TranspositionTable::build(1 << 12, ReplacementPolicy::DepthPreferred);
+            for depth in 1..=5 {
+                let score = alpha_beta_score(&state, -INF, INF, depth);
+                let mut table = TranspositionTable::build(1 << 12, ReplacementPolicy::Always);
+                let (_, table_score) = alpha_beta_action_with_transposition_table(&state, depth, &time_keeper, &mut table, &mut SearchStatistics::default()).unwrap();
+                assert_eq!(table_score, score);
+                let (_, table_score) = alpha_beta_action_with_transposition_table(&state, depth, &time_keeper, &mut shared_table, &mut SearchStatistics::default()).unwrap();
+                assert_eq!(table_score, score);
+            }
+        }
+    }
+    #[test]
+    fn table_bound_is_classified_by_the_original_window() {
+        // 置換表の下界が真の値そのものなら、窓を狭めて読んだ結果は元の窓 (-INF, INF) の中にあるので、真の値として登録し直す。
+        let time_keeper = TimeKeeper::build(f64::INFINITY);
+        for seed in 0..5 {
+            let state = GameState::new(seed);
+            let depth = 4;
+            let score = alpha_beta_score(&state, -INF, INF, depth);
+            let mut table = TranspositionTable::build(1 << 12, ReplacementPolicy::DepthPreferred);
+            table.insert(state.hash(), depth, AlphaBetaEntry { score, bound: Bound::Lower, best_action: None });
+            assert_eq!(alpha_beta_score_with_transposition_table(&state, -INF, INF, depth, &time_keeper, &mut table, &mut SearchStatistics::default()), score);
+            let (entry_depth, entry) = table.get(state.hash()).unwrap();
+            assert_eq!((entry_depth, entry.score, entry.bound), (depth, score, Bound::Exact));
+        }
+    }
+
+    #[test]
+    fn transposition_table_mcts_expands_each_position_once() {
+        // expand_threshold が playout の回数より大きければ、根だけを一度展開する。
+        for seed in 0..3 {
+            let state = GameState::new(seed);
+            let mut table = TranspositionTable::build(1 << 12, ReplacementPolicy::DepthPreferred);
+            let mut statistics = SearchStatistics::default();
+            mcts_action_with_transposition_table_and_observer(&state, 200, 1.0, 1000, &mut table, seed, &mut statistics, &mut NoObserver);
+            assert_eq!(statistics.expanded_node_number, 1);
+            assert_eq!(statistics.generated_state_number, state.legal_actions().len());
+            // 閾値に達した局面だけを展開するので、展開した数は playout の回数を閾値で割った数を深さ倍した数を超えない。
+            let mut statistics = SearchStatistics::default();
+            mcts_action_with_transposition_table_and_observer(&state, 1000, 1.0, 10, &mut table, seed, &mut statistics, &mut NoObserver);
+            assert!(statistics.expanded_node_number <= 1000 / 10 * statistics.max_depth, "{}", statistics);
+        }
+    }
+}
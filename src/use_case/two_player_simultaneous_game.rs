@@ -0,0 +1,570 @@
+use rand::*;
+
+use crate::search_observer::{NoObserver, SearchControl, SearchEvent, SearchObserver};
+use crate::search_statistics::SearchStatistics;
+use crate::statistics::ArenaResult;
+use crate::time_keeper::TimeKeeper;
+
+const H: usize = 5;
+const W: usize = 5;
+
+#[allow(unused)]
+#[derive(Clone, Copy)]
+pub struct Player {
+    x: usize,
+    y: usize,
+    game_score: isize,
+}
+#[allow(unused)]
+impl Player {
+    pub fn new(x: usize, y: usize) -> Self {
+        // (x, y) に置かれた、得点 0 の player を返す関数
+        Self {
+            x,
+            y,
+            game_score: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+#[allow(unused)]
+pub struct Board {
+    points: [[isize; W]; H],
+}
+#[allow(unused)]
+impl Board {
+    pub fn new(seed: u64) -> Self {
+        let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+        // 初期化された盤面を返す関数。
+        let mut points = [[0; W]; H];
+        for points in points.iter_mut() {
+            for point in points.iter_mut() {
+                *point = (rng.gen::<usize>() % 10) as isize;
+            }
+        }
+        Self {
+            points,
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    D(usize, usize),
+}
+#[allow(unused)]
+const ACTIONS: [Action; 4] = [Action::D(1, 0), Action::D(!0, 0), Action::D(0, 1), Action::D(0, !0)];
+
+#[allow(unused)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum WinningStatus {
+    // 先手から見た勝敗。game が終了していなければ None。
+    Win,
+    Lose,
+    Draw,
+    None,
+}
+
+#[derive(Clone, Copy)]
+#[allow(unused)]
+pub struct GameState {
+    board: Board,
+    turn: usize,
+    // players[0] が先手、players[1] が後手を表す。
+    players: [Player; 2],
+    end_turn: usize,
+}
+#[allow(unused)]
+impl GameState {
+    pub fn new(seed: u64) -> Self {
+        // GameState をシード値を用いて作成する。
+        let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+        let end_turn = 10;
+        let mut board = Board::new(rng.gen::<u64>());
+        let players = [Player::new(H / 2, W / 2 - 1), Player::new(H / 2, W / 2 + 1)];
+        for player in players.iter() {
+            board.points[player.x][player.y] = 0;
+        }
+        Self {
+            board,
+            turn: 0,
+            players,
+            end_turn,
+        }
+    }
+    pub fn build(board: Board, players: [Player; 2], end_turn: usize) -> Self {
+        // GameState を情報が与えられた前提で作成する。
+        Self {
+            board,
+            turn: 0,
+            players,
+            end_turn,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        // game が終了しているかどうかを返す。
+        self.turn == self.end_turn
+    }
+
+    fn advance(&mut self, first_action: Action, second_action: Action) {
+        // 先手が first_action、後手が second_action を同時に行うことで game を一手進める関数。
+        // 両者が同じマスに移動した場合は、両者ともそのマスの得点を得る。
+        for (player, action) in self.players.iter_mut().zip([first_action, second_action]) {
+            let Action::D(dx, dy) = action;
+            player.x = player.x.wrapping_add(dx);
+            player.y = player.y.wrapping_add(dy);
+            assert!(player.x < H && player.y < W);
+            player.game_score += self.board.points[player.x][player.y];
+        }
+        for player in self.players.iter() {
+            self.board.points[player.x][player.y] = 0;
+        }
+        self.turn += 1;
+    }
+
+    fn legal_actions(&self, player_id: usize) -> Vec<Action> {
+        // ACTIONS 全体の中で players[player_id] に許容される action の全体を返す関数。
+        let player = &self.players[player_id];
+        ACTIONS.into_iter().filter(
+            |&Action::D(dx, dy)| (player.x.wrapping_add(dx) < H) && (player.y.wrapping_add(dy) < W)
+        ).collect::<Vec<Action>>()
+    }
+
+    fn get_score(&self) -> isize {
+        // 先手から見た盤面の評価値として、得点の差を返す。
+        self.players[0].game_score - self.players[1].game_score
+    }
+
+    fn get_winning_status(&self) -> WinningStatus {
+        // 先手から見た勝敗を返す。
+        if !self.is_done() {
+            return WinningStatus::None;
+        }
+        match self.get_score().cmp(&0) {
+            std::cmp::Ordering::Greater => WinningStatus::Win,
+            std::cmp::Ordering::Less => WinningStatus::Lose,
+            std::cmp::Ordering::Equal => WinningStatus::Draw,
+        }
+    }
+
+    fn get_first_player_score_for_win_rate(&self) -> f64 {
+        // 先手から見た勝ち点を返す。勝ちなら 1、負けなら 0、それ以外は 0.5。
+        match self.get_winning_status() {
+            WinningStatus::Win => 1.0,
+            WinningStatus::Lose => 0.0,
+            _ => 0.5,
+        }
+    }
+}
+
+// ここから先は、action を決定する方法を定める関数を作る。
+#[allow(unused)]
+pub fn random_action(state: &GameState, player_id: usize, rng: &mut rand::rngs::StdRng) -> Option<Action> {
+    let legal_actions = state.legal_actions(player_id);
+    if legal_actions.is_empty() {
+        return None;
+    }
+    Some(legal_actions[rng.gen::<usize>() % legal_actions.len()])
+}
+#[allow(unused)]
+fn playout(state: &mut GameState, rng: &mut rand::rngs::StdRng) -> f64 {
+    // 両者が random_action を選び続けたときの、先手から見た勝ち点を返す。
+    while !state.is_done() {
+        match (random_action(state, 0, rng), random_action(state, 1, rng)) {
+            (Some(first_action), Some(second_action)) => state.advance(first_action, second_action),
+            _ => break,
+        }
+    }
+    state.get_first_player_score_for_win_rate()
+}
+#[allow(unused)]
+fn primitive_monte_carlo_score(state: &GameState, player_id: usize, action: Action, rng: &mut rand::rngs::StdRng) -> f64 {
+    // players[player_id] が action を行い、相手が random_action を行った後に playout した、players[player_id] から見た勝ち点を返す。
+    let mut next_state = *state;
+    let opponent_action = match random_action(state, player_id ^ 1, rng) {
+        Some(opponent_action) => opponent_action,
+        None => return 0.5,
+    };
+    if player_id == 0 {
+        next_state.advance(action, opponent_action);
+    } else {
+        next_state.advance(opponent_action, action);
+    }
+    let value = playout(&mut next_state, rng);
+    if player_id == 0 {
+        value
+    } else {
+        1.0 - value
+    }
+}
+#[allow(unused)]
+pub fn primitive_monte_carlo_action(state: &GameState, player_id: usize, playout_number: usize, seed: u64) -> Option<Action> {
+    primitive_monte_carlo_action_with_observer(state, player_id, playout_number, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn primitive_monte_carlo_action_with_observer(state: &GameState, player_id: usize, playout_number: usize, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // players[player_id] の合法手を順番に選んで playout し、平均の勝ち点が最大の action を返す。
+    // 統計の生成した状態の数は playout の回数になる。
+    // playout を一回行うたびに observer に全ての playout の平均の勝ち点を Iteration として送り、Abort が返されたらそこまでの結果で action を選ぶ。
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let legal_actions = state.legal_actions(player_id);
+    if legal_actions.is_empty() {
+        statistics.finish(start_time);
+        return None;
+    }
+    let mut w = vec![0.0; legal_actions.len()];
+    let mut n = vec![0; legal_actions.len()];
+    statistics.expanded_node_number += 1;
+    statistics.record_depth(1);
+    let mut w_sum = 0.0;
+    for i in 0..playout_number {
+        let k = i % legal_actions.len();
+        let value = primitive_monte_carlo_score(state, player_id, legal_actions[k], &mut rng);
+        w[k] += value;
+        n[k] += 1;
+        w_sum += value;
+        statistics.generated_state_number += 1;
+        if observer.on_event(&SearchEvent::Iteration { iteration: i, score: w_sum / (i + 1) as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    best_average_action(&legal_actions, &w, &n)
+}
+#[allow(unused)]
+pub fn primitive_monte_carlo_action_with_time_threshold(state: &GameState, player_id: usize, time_threshold: f64, seed: u64) -> Option<Action> {
+    primitive_monte_carlo_action_with_time_threshold_and_observer(state, player_id, time_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused)]
+fn primitive_monte_carlo_action_with_time_threshold_and_observer(state: &GameState, player_id: usize, time_threshold: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let legal_actions = state.legal_actions(player_id);
+    if legal_actions.is_empty() {
+        statistics.finish(start_time);
+        return None;
+    }
+    let mut w = vec![0.0; legal_actions.len()];
+    let mut n = vec![0; legal_actions.len()];
+    statistics.expanded_node_number += 1;
+    statistics.record_depth(1);
+    let mut w_sum = 0.0;
+    for i in 0.. {
+        if time_keeper.is_time_over() {
+            break;
+        }
+        let k = i % legal_actions.len();
+        let value = primitive_monte_carlo_score(state, player_id, legal_actions[k], &mut rng);
+        w[k] += value;
+        n[k] += 1;
+        w_sum += value;
+        statistics.generated_state_number += 1;
+        if observer.on_event(&SearchEvent::Iteration { iteration: i, score: w_sum / (i + 1) as f64 }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    best_average_action(&legal_actions, &w, &n)
+}
+#[allow(unused)]
+fn best_average_action(legal_actions: &[Action], w: &[f64], n: &[usize]) -> Option<Action> {
+    let mut best_action = None;
+    let mut best_value = -1.0;
+    for k in 0..legal_actions.len() {
+        let value = if n[k] == 0 { 0.0 } else { w[k] / n[k] as f64 };
+        if best_action.is_none() || value > best_value {
+            best_action = Some(legal_actions[k]);
+            best_value = value;
+        }
+    }
+    best_action
+}
+#[allow(unused)]
+struct Node {
+    // Decoupled UCT の節点。w は先手から見た勝ち点の和、n は訪問回数を表す。
+    // child_nodes[i][j] は、先手が actions[0][i]、後手が actions[1][j] を行った後の節点。
+    state: GameState,
+    w: f64,
+    n: usize,
+    actions: [Vec<Action>; 2],
+    child_nodes: Vec<Vec<Node>>,
+}
+#[allow(unused)]
+impl Node {
+    fn new(state: GameState) -> Self {
+        Self {
+            state,
+            w: 0.0,
+            n: 0,
+            actions: [Vec::new(), Vec::new()],
+            child_nodes: Vec::new(),
+        }
+    }
+
+    fn evaluate(&mut self, c: f64, expand_threshold: usize, depth: usize, rng: &mut rand::rngs::StdRng, statistics: &mut SearchStatistics) -> f64 {
+        // この節点から一回だけ探索し、先手から見た勝ち点を返す。
+        // 子を持たない節点は playout で評価し、訪問回数が expand_threshold に達したら展開する。
+        statistics.record_depth(depth);
+        let value = if self.state.is_done() || self.child_nodes.is_empty() {
+            let value = playout(&mut self.state.clone(), rng);
+            if !self.state.is_done() && self.n + 1 >= expand_threshold {
+                self.expand(statistics);
+            }
+            value
+        } else {
+            let (i, j) = (self.next_action_index(0, c), self.next_action_index(1, c));
+            self.child_nodes[i][j].evaluate(c, expand_threshold, depth + 1, rng, statistics)
+        };
+        self.w += value;
+        self.n += 1;
+        value
+    }
+
+    fn expand(&mut self, statistics: &mut SearchStatistics) {
+        self.actions = [self.state.legal_actions(0), self.state.legal_actions(1)];
+        self.child_nodes = self.actions[0]
+            .iter()
+            .map(|&first_action| {
+                self.actions[1]
+                    .iter()
+                    .map(|&second_action| {
+                        let mut next_state = self.state;
+                        next_state.advance(first_action, second_action);
+                        Node::new(next_state)
+                    })
+                    .collect()
+            })
+            .collect();
+        if self.child_nodes.iter().all(|child_nodes| child_nodes.is_empty()) {
+            self.child_nodes.clear();
+        }
+        statistics.expanded_node_number += 1;
+        statistics.generated_state_number += self.child_nodes.iter().map(|child_nodes| child_nodes.len()).sum::<usize>();
+    }
+
+    fn action_statistics(&self, player_id: usize) -> Vec<(f64, usize)> {
+        // players[player_id] の action ごとに、相手の action について和をとった (players[player_id] から見た勝ち点の和, 訪問回数) を返す。
+        let mut statistics = vec![(0.0, 0); self.actions[player_id].len()];
+        for (i, child_nodes) in self.child_nodes.iter().enumerate() {
+            for (j, child_node) in child_nodes.iter().enumerate() {
+                let (k, w) = if player_id == 0 { (i, child_node.w) } else { (j, child_node.n as f64 - child_node.w) };
+                statistics[k].0 += w;
+                statistics[k].1 += child_node.n;
+            }
+        }
+        statistics
+    }
+
+    fn next_action_index(&self, player_id: usize, c: f64) -> usize {
+        // players[player_id] の action のうち、選ばれていないものがあればそれを、なければ UCB1 が最大のものを返す。
+        let statistics = self.action_statistics(player_id);
+        if let Some(k) = statistics.iter().position(|&(_, n)| n == 0) {
+            return k;
+        }
+        let t = statistics.iter().map(|&(_, n)| n).sum::<usize>() as f64;
+        let ucb1 = |(w, n): (f64, usize)| w / n as f64 + c * (2.0 * t.ln() / n as f64).sqrt();
+        let mut best_k = 0;
+        for k in 1..statistics.len() {
+            if ucb1(statistics[k]) > ucb1(statistics[best_k]) {
+                best_k = k;
+            }
+        }
+        best_k
+    }
+
+    fn average_score(&self, player_id: usize) -> f64 {
+        // players[player_id] から見た平均の勝ち点を返す。
+        let value = self.w / self.n.max(1) as f64;
+        if player_id == 0 {
+            value
+        } else {
+            1.0 - value
+        }
+    }
+
+    fn most_visited_action(&self, player_id: usize) -> Option<Action> {
+        let statistics = self.action_statistics(player_id);
+        let mut best_action = None;
+        let mut best_n = 0;
+        for (&action, &(_, n)) in self.actions[player_id].iter().zip(statistics.iter()) {
+            if best_action.is_none() || n > best_n {
+                best_action = Some(action);
+                best_n = n;
+            }
+        }
+        best_action
+    }
+}
+#[allow(unused)]
+pub fn duct_action(state: &GameState, player_id: usize, playout_number: usize, c: f64, expand_threshold: usize, seed: u64) -> Option<Action> {
+    duct_action_with_observer(state, player_id, playout_number, c, expand_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn duct_action_with_observer(state: &GameState, player_id: usize, playout_number: usize, c: f64, expand_threshold: usize, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    // Decoupled UCT で players[player_id] の action を決める。
+    // c は UCB1 の探索の強さ、expand_threshold は節点を展開するまでの訪問回数を表す。
+    // 一回探索するたびに observer に players[player_id] から見た根の平均の勝ち点を Iteration として送り、Abort が返されたらそこまでの統計で action を選ぶ。
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut root_node = Node::new(*state);
+    root_node.expand(statistics);
+    for iteration in 0..playout_number {
+        root_node.evaluate(c, expand_threshold, 0, &mut rng, statistics);
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: root_node.average_score(player_id) }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    root_node.most_visited_action(player_id)
+}
+#[allow(unused)]
+pub fn duct_action_with_time_threshold(state: &GameState, player_id: usize, c: f64, expand_threshold: usize, time_threshold: f64, seed: u64) -> Option<Action> {
+    duct_action_with_time_threshold_and_observer(state, player_id, c, expand_threshold, time_threshold, seed, &mut SearchStatistics::default(), &mut NoObserver)
+}
+#[allow(unused, clippy::too_many_arguments)]
+fn duct_action_with_time_threshold_and_observer(state: &GameState, player_id: usize, c: f64, expand_threshold: usize, time_threshold: f64, seed: u64, statistics: &mut SearchStatistics, observer: &mut dyn SearchObserver) -> Option<Action> {
+    let time_keeper = TimeKeeper::build(time_threshold);
+    let start_time = std::time::Instant::now();
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut root_node = Node::new(*state);
+    root_node.expand(statistics);
+    for iteration in 0.. {
+        if time_keeper.is_time_over() {
+            break;
+        }
+        root_node.evaluate(c, expand_threshold, 0, &mut rng, statistics);
+        if observer.on_event(&SearchEvent::Iteration { iteration, score: root_node.average_score(player_id) }) == SearchControl::Abort {
+            break;
+        }
+    }
+    statistics.finish(start_time);
+    root_node.most_visited_action(player_id)
+}
+
+#[allow(unused)]
+pub fn play_game(seed: u64) -> f64 {
+    // 先手が duct_action、後手が primitive_monte_carlo_action で対戦し、先手から見た勝ち点を返す。
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut state = GameState::new(rng.gen::<u64>());
+    while !state.is_done() {
+        match (duct_action(&state, 0, 1000, 1.0, 10, rng.gen::<u64>()), primitive_monte_carlo_action(&state, 1, 1000, rng.gen::<u64>())) {
+            (Some(first_action), Some(second_action)) => state.advance(first_action, second_action),
+            _ => break,
+        }
+    }
+    state.get_first_player_score_for_win_rate()
+}
+#[allow(unused)]
+pub fn test_first_player_win_rate(game_number: usize, seed: u64) -> f64 {
+    let mut rngs: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut score_sum = 0.0;
+    for _ in 0..game_number {
+        score_sum += play_game(rngs.gen::<u64>());
+    }
+    score_sum / game_number as f64
+}
+#[allow(unused)]
+fn play_arena_game(seed: u64, first_ai: &dyn Fn(&GameState, usize, u64) -> Option<Action>, second_ai: &dyn Fn(&GameState, usize, u64) -> Option<Action>) -> GameState {
+    // first_ai を先手、second_ai を後手として一局対戦させ、終局した状態を返す。
+    // AI には自分の player_id と、手番ごとに seed から作ったシード値を渡すので、同じ seed の対局は再現できる。
+    let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(seed);
+    let mut state = GameState::new(rng.gen::<u64>());
+    while !state.is_done() {
+        match (first_ai(&state, 0, rng.gen::<u64>()), second_ai(&state, 1, rng.gen::<u64>())) {
+            (Some(first_action), Some(second_action)) => state.advance(first_action, second_action),
+            _ => break,
+        }
+    }
+    state
+}
+#[allow(unused)]
+pub fn arena(ai: &dyn Fn(&GameState, usize, u64) -> Option<Action>, opponent_ai: &dyn Fn(&GameState, usize, u64) -> Option<Action>, game_number: usize, seed: u64) -> ArenaResult {
+    // game_number 個のシードそれぞれで、ai と opponent_ai の先手と後手を入れ替えて二局ずつ対戦させる。
+    ArenaResult::play(game_number, seed, &|game_seed, is_first| {
+        let state = if is_first { play_arena_game(game_seed, ai, opponent_ai) } else { play_arena_game(game_seed, opponent_ai, ai) };
+        (state.get_first_player_score_for_win_rate(), state.get_score() as f64)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOWN: Action = Action::D(1, 0);
+    const UP: Action = Action::D(!0, 0);
+
+    fn dominant_action_position() -> GameState {
+        // 一手で終わる game で、先手は上、後手は下のマス (5 点) を取るのが、相手の action によらず最善になる。
+        let mut board = Board { points: [[0; W]; H] };
+        board.points[1][1] = 5;
+        board.points[3][3] = 5;
+        GameState::build(board, [Player::new(2, 1), Player::new(2, 3)], 1)
+    }
+
+    #[test]
+    fn searches_choose_the_dominant_action() {
+        let state = dominant_action_position();
+        for seed in 0..5 {
+            assert!(duct_action(&state, 0, 1000, 1.0, 10, seed) == Some(UP));
+            assert!(duct_action(&state, 1, 1000, 1.0, 10, seed) == Some(DOWN));
+            assert!(primitive_monte_carlo_action(&state, 0, 400, seed) == Some(UP));
+            assert!(primitive_monte_carlo_action(&state, 1, 400, seed) == Some(DOWN));
+        }
+    }
+
+    #[test]
+    fn action_statistics_are_from_each_player_perspective() {
+        // 先手の上と後手の下は、相手の action について和をとっても、それぞれから見て他の action より勝ち点の平均が高い。
+        // 後手の勝ち点は先手から見た勝ち点を反転したものなので、同じ子の勝ち点を両者で足すと訪問回数になる。
+        let state = dominant_action_position();
+        let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(0);
+        let mut root_node = Node::new(state);
+        root_node.expand(&mut SearchStatistics::default());
+        for _ in 0..1000 {
+            root_node.evaluate(1.0, 10, 0, &mut rng, &mut SearchStatistics::default());
+        }
+        for (player_id, dominant_action) in [(0, UP), (1, DOWN)] {
+            let statistics = root_node.action_statistics(player_id);
+            let k = root_node.actions[player_id].iter().position(|&action| action == dominant_action).unwrap();
+            let average = |(w, n): (f64, usize)| w / n as f64;
+            for (other_k, &other_statistics) in statistics.iter().enumerate().filter(|&(other_k, _)| other_k != k) {
+                assert!(average(statistics[k]) > average(other_statistics), "player {}: {:?} at {}", player_id, statistics, other_k);
+            }
+        }
+        let w_sum = |player_id: usize| root_node.action_statistics(player_id).iter().map(|&(w, _)| w).sum::<f64>();
+        assert_eq!(w_sum(0) + w_sum(1), root_node.n as f64);
+    }
+
+    #[test]
+    fn searches_are_deterministic_for_a_seed() {
+        let state = GameState::new(0);
+        let run = |seed: u64, is_duct: bool| {
+            let mut scores = Vec::new();
+            let mut observer = |event: &SearchEvent| {
+                if let SearchEvent::Iteration { score, .. } = *event {
+                    scores.push(score);
+                }
+                SearchControl::Continue
+            };
+            let action = if is_duct {
+                duct_action_with_observer(&state, 1, 300, 1.0, 10, seed, &mut SearchStatistics::default(), &mut observer)
+            } else {
+                primitive_monte_carlo_action_with_observer(&state, 1, 300, seed, &mut SearchStatistics::default(), &mut observer)
+            };
+            (action, scores)
+        };
+        for seed in 0..5 {
+            for is_duct in [true, false] {
+                let (action, scores) = run(seed, is_duct);
+                let (other_action, other_scores) = run(seed, is_duct);
+                assert!(action == other_action);
+                assert_eq!(scores, other_scores);
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// 一マスを何ピクセルで描くか。
+#[allow(unused)]
+pub const CELL_SIZE: usize = 20;
+
+#[allow(unused)]
+#[derive(Clone, Debug)]
+pub struct GridFrame {
+    pub turn: usize,
+    pub score: isize,
+    // cells[x][y] は上から x 行目、左から y 列目のマスの値。
+    pub cells: Vec<Vec<isize>>,
+    // player が通ったマスの列。最後の要素が今いるマス。
+    pub path: Vec<(usize, usize)>,
+}
+
+#[allow(unused)]
+pub fn render_svg(frame: &GridFrame, max_value: isize) -> String {
+    // 値が大きいマスほど濃い色で塗り、通った道を線で、今いるマスを円で描く。
+    let height = frame.cells.len();
+    let width = frame.cells.first().map_or(0, |cells| cells.len());
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        width * CELL_SIZE,
+        height * CELL_SIZE + CELL_SIZE,
+        width * CELL_SIZE,
+        height * CELL_SIZE + CELL_SIZE
+    );
+    svg += &format!("<text x=\"4\" y=\"{}\" font-size=\"14\" font-family=\"monospace\">turn {} score {}</text>\n", CELL_SIZE - 5, frame.turn, frame.score);
+    svg += &format!("<g transform=\"translate(0, {})\">\n", CELL_SIZE);
+    for (x, cells) in frame.cells.iter().enumerate() {
+        for (y, &value) in cells.iter().enumerate() {
+            let ratio = if max_value > 0 { value.clamp(0, max_value) as f64 / max_value as f64 } else { 0.0 };
+            let color = (255.0 * (1.0 - 0.7 * ratio)) as u8;
+            svg += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"rgb({},255,{})\" stroke=\"#ccc\"/>",
+                y * CELL_SIZE,
+                x * CELL_SIZE,
+                CELL_SIZE,
+                CELL_SIZE,
+                color,
+                color
+            );
+            if value != 0 {
+                svg += &format!(
+                    "<text x=\"{}\" y=\"{}\" font-size=\"10\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>",
+                    y * CELL_SIZE + CELL_SIZE / 2,
+                    x * CELL_SIZE + CELL_SIZE / 2,
+                    value
+                );
+            }
+            svg += "\n";
+        }
+    }
+    let center = |&(x, y): &(usize, usize)| (y * CELL_SIZE + CELL_SIZE / 2, x * CELL_SIZE + CELL_SIZE / 2);
+    if frame.path.len() > 1 {
+        let points = frame.path.iter().map(center).map(|(cx, cy)| format!("{},{}", cx, cy)).collect::<Vec<_>>();
+        svg += &format!("<polyline points=\"{}\" fill=\"none\" stroke=\"#d33\" stroke-width=\"3\" stroke-opacity=\"0.7\"/>\n", points.join(" "));
+    }
+    if let Some(position) = frame.path.last() {
+        let (cx, cy) = center(position);
+        svg += &format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"#d33\"/>\n", cx, cy, CELL_SIZE / 3);
+    }
+    svg += "</g>\n</svg>\n";
+    svg
+}
+
+#[allow(unused)]
+fn escape_html(text: &str) -> String {
+    // HTML の要素の中に文字列をそのまま書けるように、特別な意味を持つ文字を置き換える。
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[allow(unused)]
+pub fn render_html(frames: &[GridFrame], max_value: isize, title: &str) -> String {
+    // 各ターンの SVG を埋め込み、スライダーとボタンで一枚ずつ表示する単独の HTML を返す。
+    let title = escape_html(title);
+    let mut html = format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n", title);
+    html += "<style>body { font-family: sans-serif; } .frame { display: none; } .frame.shown { display: block; }</style>\n</head>\n<body>\n";
+    html += &format!("<h1>{}</h1>\n", title);
+    html += &format!(
+        "<div><button id=\"prev\">&lt;</button> <button id=\"play\">play</button> <button id=\"next\">&gt;</button> <input id=\"turn\" type=\"range\" min=\"0\" max=\"{}\" value=\"0\"></div>\n",
+        frames.len().saturating_sub(1)
+    );
+    for frame in frames.iter() {
+        html += "<div class=\"frame\">\n";
+        html += &render_svg(frame, max_value);
+        html += "</div>\n";
+    }
+    html += r#"<script>
+const frames = document.querySelectorAll(".frame");
+const slider = document.getElementById("turn");
+let timer = null;
+function show(index) {
+    index = Math.max(0, Math.min(frames.length - 1, index));
+    frames.forEach((frame, i) => frame.classList.toggle("shown", i === index));
+    slider.value = index;
+}
+slider.addEventListener("input", () => show(Number(slider.value)));
+document.getElementById("prev").addEventListener("click", () => show(Number(slider.value) - 1));
+document.getElementById("next").addEventListener("click", () => show(Number(slider.value) + 1));
+document.getElementById("play").addEventListener("click", () => {
+    if (timer !== null) {
+        clearInterval(timer);
+        timer = null;
+        return;
+    }
+    if (Number(slider.value) === frames.length - 1) {
+        show(0);
+    }
+    timer = setInterval(() => {
+        if (Number(slider.value) === frames.length - 1) {
+            clearInterval(timer);
+            timer = null;
+        } else {
+            show(Number(slider.value) + 1);
+        }
+    }, 300);
+});
+show(0);
+</script>
+</body>
+</html>
+"#;
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_is_escaped() {
+        let html = render_html(&[], 9, "<script>alert(\"a & b\")</script>");
+        assert!(!html.contains("<script>alert"));
+        assert!(html.contains("<title>&lt;script&gt;alert(&quot;a &amp; b&quot;)&lt;/script&gt;</title>"));
+    }
+}